@@ -0,0 +1,13 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use aurora_engine::transaction::EthSignedTransaction;
+use rlp::{Decodable, Rlp};
+
+fuzz_target!(|data: &[u8]| {
+    // Decoding and sender recovery must never panic, whatever the input.
+    if let Ok(signed_transaction) = EthSignedTransaction::decode(&Rlp::new(data)) {
+        let _ = signed_transaction.chain_id();
+        let _ = signed_transaction.sender();
+    }
+});
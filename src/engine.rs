@@ -1,18 +1,27 @@
 use borsh::{BorshDeserialize, BorshSerialize};
 use evm::backend::{Apply, ApplyBackend, Backend, Basic, Log};
 use evm::executor::{MemoryStackState, StackExecutor, StackSubstateMetadata};
-use evm::{Config, CreateScheme, ExitFatal, ExitReason};
+use evm::{Config, CreateScheme, ExitError, ExitReason};
 
-use crate::parameters::{FunctionCallArgs, NewCallArgs, ViewCallArgs};
+#[cfg(feature = "evm_bully")]
+use crate::parameters::BeginBlockArgs;
+use crate::parameters::{
+    DerivationScheme, EvmConfigResult, FunctionCallArgs, LegacyNewCallArgs, LogLevel, NewCallArgs,
+    PrecompileInfo, RateLimit, ResultLog, SubmitResult, ViewCallArgs,
+};
 use crate::precompiles;
-use crate::prelude::{Address, Vec, H256, U256};
+use crate::prelude::{vec, Address, Borrowed, Vec, H256, U256};
 use crate::sdk;
-use crate::storage::{address_to_key, storage_to_key, KeyPrefix};
-use crate::types::{bytes_to_hex, log_to_bytes, u256_to_arr, AccountId};
+use crate::storage::{address_to_key, block_hash_to_key, storage_to_key, KeyPrefix};
+use crate::types::{
+    bytes_to_hex, keccak, log_to_bytes, u256_to_arr, AccountId, ErrorKind, RawAddress, RawH256,
+    RawU256,
+};
+use core::cell::RefCell;
 
 /// Engine internal state, mostly configuration.
 /// Should not contain anything large or enumerable.
-#[derive(BorshSerialize, BorshDeserialize, Default)]
+#[derive(BorshSerialize, BorshDeserialize)]
 pub struct EngineState {
     /// Chain id, according to the EIP-115 / ethereum-lists spec.
     pub chain_id: [u8; 32],
@@ -24,6 +33,45 @@ pub struct EngineState {
     pub bridge_prover_id: AccountId,
     /// How many blocks after staging upgrade can deploy it.
     pub upgrade_delay_blocks: u64,
+    /// Maximum size in bytes of the EVM input accepted by `view`.
+    pub max_view_input_size: u64,
+    /// Address returned by the COINBASE opcode as the block's beneficiary.
+    pub fee_recipient: RawAddress,
+    /// Maximum number of logs a transaction may emit; at least 1.
+    pub max_logs_per_transaction: u64,
+    /// Whether `raw_call` rejects transactions without an EIP-155 chain id.
+    pub require_chain_id: bool,
+    /// How NEAR account ids map to EVM addresses.
+    pub derivation_scheme: DerivationScheme,
+    /// Verbosity of the engine's diagnostic messages.
+    pub log_level: LogLevel,
+    /// Lowest gas price `raw_call` accepts transactions with.
+    pub min_gas_price: RawU256,
+    /// Maximum size in bytes of the EVM return data of a call or view; at
+    /// least 1 and at most `MAX_RETURN_DATA_SIZE`.
+    pub max_return_size: u64,
+    /// Whether calls and transfers to the zero address are rejected rather
+    /// than executed as an ordinary call to it.
+    pub reject_send_to_zero: bool,
+    /// Limit on the number of transactions of each sender, if any.
+    pub rate_limit: Option<RateLimit>,
+    /// Whether `call` fails for a target without code rather than succeeding
+    /// without doing anything, to surface misconfigured target addresses.
+    pub reject_call_without_code: bool,
+}
+
+impl Default for EngineState {
+    fn default() -> Self {
+        NewCallArgs::default().into()
+    }
+}
+
+impl EngineState {
+    /// Whether `new` has been called. An engine without an owner counts as
+    /// uninitialized, as anyone may still call `new` on it.
+    pub fn is_initialized(&self) -> bool {
+        !self.owner_id.is_empty()
+    }
 }
 
 impl From<NewCallArgs> for EngineState {
@@ -33,6 +81,54 @@ impl From<NewCallArgs> for EngineState {
             owner_id: args.owner_id,
             bridge_prover_id: args.bridge_prover_id,
             upgrade_delay_blocks: args.upgrade_delay_blocks,
+            max_view_input_size: args.max_view_input_size,
+            fee_recipient: args.fee_recipient,
+            max_logs_per_transaction: args.max_logs_per_transaction,
+            require_chain_id: args.require_chain_id,
+            derivation_scheme: args.derivation_scheme,
+            log_level: args.log_level,
+            min_gas_price: args.min_gas_price,
+            max_return_size: args.max_return_size,
+            reject_send_to_zero: args.reject_send_to_zero,
+            rate_limit: args.rate_limit,
+            reject_call_without_code: args.reject_call_without_code,
+        }
+    }
+}
+
+/// Block-scoped state, advanced by `begin_block` when benchmarking.
+/// Outside of benchmarking the block context comes from the NEAR runtime.
+#[derive(BorshSerialize, BorshDeserialize, Default)]
+pub struct BlockState {
+    /// The current block's hash.
+    pub hash: RawH256,
+    /// The current block's beneficiary address.
+    pub coinbase: RawAddress,
+    /// The current block's timestamp (in seconds since the Unix epoch).
+    pub timestamp: RawU256,
+    /// The current block's number.
+    pub number: RawU256,
+    /// The current block's difficulty.
+    pub difficulty: RawU256,
+    /// The current block's gas limit.
+    pub gas_limit: RawU256,
+    /// The EVM gas used so far by the current block's transactions.
+    pub gas_used: u64,
+}
+
+#[cfg(feature = "evm_bully")]
+impl From<BeginBlockArgs> for BlockState {
+    fn from(args: BeginBlockArgs) -> Self {
+        let mut coinbase = [0u8; 20];
+        coinbase.copy_from_slice(&args.coinbase[12..]);
+        BlockState {
+            hash: args.hash,
+            coinbase,
+            timestamp: args.timestamp,
+            number: args.number,
+            difficulty: args.difficulty,
+            gas_limit: args.gaslimit,
+            gas_used: 0,
         }
     }
 }
@@ -40,33 +136,243 @@ impl From<NewCallArgs> for EngineState {
 pub struct Engine {
     state: EngineState,
     origin: Address,
+    block: Option<BlockState>,
+    code_cache: RefCell<CodeCache>,
+    storage_counts: StorageCounts,
+}
+
+/// How many storage slots the last applied transaction wrote a non-zero value
+/// to, and how many it wrote zero to, which its NEAR storage staking cost
+/// depends on.
+/// Storage removed with a whole account, by self-destruction or a reset of its
+/// storage, isn't counted: it can't be enumerated, and so isn't removed yet.
+#[derive(Clone, Copy, Default)]
+struct StorageCounts {
+    writes: u64,
+    zero_writes: u64,
+}
+
+/// Number of codes kept by `CodeCache`.
+const CODE_CACHE_SIZE: usize = 8;
+
+/// The most recently read codes, most recent first, so that a transaction
+/// calling the same contracts repeatedly reads each code from storage once,
+/// though `code` still copies it out of the cache for each call.
+/// It is cleared before each transaction: the codes in storage only change
+/// when a transaction's changes are applied, after it has run.
+#[derive(Default)]
+struct CodeCache {
+    entries: Vec<(Address, Vec<u8>)>,
+}
+
+impl CodeCache {
+    /// Returns the code of `address`, reading it from storage if not cached.
+    fn get(&mut self, address: &Address) -> &[u8] {
+        match self
+            .entries
+            .iter()
+            .position(|(cached, _)| cached == address)
+        {
+            Some(index) => {
+                let entry = self.entries.remove(index);
+                self.entries.insert(0, entry);
+            }
+            None => {
+                self.entries.truncate(CODE_CACHE_SIZE - 1);
+                self.entries
+                    .insert(0, (*address, Engine::get_code(address)));
+            }
+        }
+        &self.entries[0].1
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+    }
 }
 
 // TODO: upgrade to Berlin HF
-const CONFIG: &Config = &Config::istanbul();
+pub(crate) const CONFIG: &Config = &Config::istanbul();
+/// Name of the hardfork of `CONFIG`.
+const HARDFORK: &str = "istanbul";
 
 /// Key for storing the state of the engine.
 const STATE_KEY: &[u8; 6] = b"\0STATE";
 
+/// Version of the layout the state of the engine is stored in, written before it.
+/// The engine's first version stored it unversioned, in the layout of
+/// `LegacyNewCallArgs`, which starts with the most significant byte of the
+/// chain id: zero for any chain id in use.
+const STATE_VERSION: u8 = 1;
+
+/// Key for storing the block-scoped state of the engine.
+const BLOCK_KEY: &[u8; 6] = b"\0BLOCK";
+
+/// Key for storing the running total of all EVM balances.
+const TOTAL_BALANCE_KEY: &[u8; 14] = b"\0TOTAL_BALANCE";
+
+/// Key for storing the index of the block a transaction was last applied in.
+const LAST_BLOCK_INDEX_KEY: &[u8; 11] = b"\0LAST_BLOCK";
+
+/// Number of most recent blocks `get_balance_at` can answer for.
+#[cfg(feature = "balance-history")]
+const BALANCE_HISTORY_BLOCKS: u64 = 64;
+
+/// Number of most recent block hashes available to the BLOCKHASH opcode.
+const BLOCK_HASH_HISTORY: u64 = 256;
+
+/// Key for storing the code staged for an upgrade.
+pub const CODE_KEY: &[u8; 5] = b"\0CODE";
+
+/// Key for storing the index of the block the upgrade code was staged in.
+const CODE_STAGE_KEY: &[u8; 11] = b"\0CODE_STAGE";
+
+/// Key for storing the hash of the code staged for an upgrade.
+const CODE_HASH_KEY: &[u8; 10] = b"\0CODE_HASH";
+
 impl Engine {
     pub fn new(origin: Address) -> Self {
         Self::new_with_state(Engine::get_state(), origin)
     }
 
     pub fn new_with_state(state: EngineState, origin: Address) -> Self {
-        Self { state, origin }
+        Self {
+            state,
+            origin,
+            block: Self::get_block_state(),
+            code_cache: RefCell::default(),
+            storage_counts: StorageCounts::default(),
+        }
     }
 
     /// Saves state into the storage.
     pub fn set_state(state: EngineState) {
-        sdk::write_storage(STATE_KEY, &state.try_to_vec().expect("ERR_SER"));
+        let bytes = [&[STATE_VERSION][..], &state.try_to_vec().expect("ERR_SER")].concat();
+        sdk::write_storage(STATE_KEY, &bytes);
     }
 
     /// Fails if state is not found.
+    /// A state stored by the engine's first version gets the current defaults
+    /// for the settings added since, and is saved in the current layout by the
+    /// next `set_state`.
     pub fn get_state() -> EngineState {
         match sdk::read_storage(STATE_KEY) {
             None => Default::default(),
-            Some(bytes) => EngineState::try_from_slice(&bytes).expect("ERR_DESER"),
+            Some(bytes) => Self::decode_state(&bytes).expect("ERR_DESER"),
+        }
+    }
+
+    fn decode_state(bytes: &[u8]) -> Option<EngineState> {
+        match bytes.split_first() {
+            Some((&STATE_VERSION, state)) => EngineState::try_from_slice(state).ok(),
+            Some((0, _)) => LegacyNewCallArgs::try_from_slice(bytes)
+                .ok()
+                .map(|args| NewCallArgs::from(args).into()),
+            _ => None,
+        }
+    }
+
+    /// Stages code for an upgrade, along with its hash and the current block index.
+    pub fn stage_upgrade(code: &[u8]) {
+        sdk::write_storage(CODE_KEY, code);
+        sdk::write_storage(CODE_HASH_KEY, keccak(code).as_bytes());
+        sdk::write_storage(CODE_STAGE_KEY, &sdk::block_index().to_le_bytes());
+    }
+
+    /// Returns the index of the block after which the staged upgrade can be
+    /// deployed, if an upgrade is staged.
+    pub fn get_upgrade_index(state: &EngineState) -> Option<u64> {
+        sdk::read_u64(CODE_STAGE_KEY).map(|index| index.saturating_add(state.upgrade_delay_blocks))
+    }
+
+    /// Clears the staged upgrade once its code is deployed, which removed the
+    /// code itself.
+    pub fn finish_upgrade() {
+        sdk::remove_storage(CODE_STAGE_KEY);
+        sdk::remove_storage(CODE_HASH_KEY);
+    }
+
+    /// Checks that the staged upgrade can be deployed now, and that the code
+    /// is the one that was staged, so that a missing or altered code is never
+    /// deployed.
+    pub fn check_staged_upgrade(state: &EngineState) -> core::result::Result<(), ErrorKind> {
+        let upgrade_index = Self::get_upgrade_index(state).ok_or(ErrorKind::NoUpgrade)?;
+        if sdk::block_index() <= upgrade_index {
+            return Err(ErrorKind::UpgradeTooEarly);
+        }
+        let code = match sdk::read_storage(CODE_KEY) {
+            Some(code) if !code.is_empty() => code,
+            _ => return Err(ErrorKind::NoUpgrade),
+        };
+        if sdk::read_storage(CODE_HASH_KEY).as_deref() != Some(keccak(&code).as_bytes()) {
+            return Err(ErrorKind::UpgradeCodeMismatch);
+        }
+        Ok(())
+    }
+
+    /// Saves the block-scoped state into the storage.
+    pub fn set_block_state(block: &BlockState) {
+        sdk::write_storage(BLOCK_KEY, &block.try_to_vec().expect("ERR_SER"));
+    }
+
+    /// Returns the block-scoped state, if a block has been begun.
+    #[cfg(feature = "evm_bully")]
+    pub fn get_block_state() -> Option<BlockState> {
+        sdk::read_storage(BLOCK_KEY)
+            .map(|bytes| BlockState::try_from_slice(&bytes).expect("ERR_DESER"))
+    }
+
+    /// Returns the block-scoped state, which is only kept when benchmarking.
+    #[cfg(not(feature = "evm_bully"))]
+    pub fn get_block_state() -> Option<BlockState> {
+        None
+    }
+
+    /// Advances the block-scoped state to a new block, recording the hash of
+    /// the previous block and resetting the block's gas counter.
+    #[cfg(feature = "evm_bully")]
+    pub fn begin_block(block: BlockState) {
+        if let Some(previous) = Self::get_block_state() {
+            Self::set_block_hash(&U256::from(previous.number), &H256(previous.hash));
+        }
+        Self::set_block_state(&block);
+    }
+
+    #[cfg(feature = "evm_bully")]
+    fn set_block_hash(number: &U256, hash: &H256) {
+        let mut value = [0u8; 64];
+        number.to_big_endian(&mut value[..32]);
+        value[32..].copy_from_slice(&hash.0);
+        let slot = number.low_u64() % BLOCK_HASH_HISTORY;
+        sdk::write_storage(&block_hash_to_key(slot), &value);
+    }
+
+    /// Returns the hash of the given block if it is one of the
+    /// `BLOCK_HASH_HISTORY` blocks preceding the current one.
+    fn get_block_hash(current: U256, number: U256) -> H256 {
+        if number >= current || current - number > U256::from(BLOCK_HASH_HISTORY) {
+            return H256::zero();
+        }
+        let slot = number.low_u64() % BLOCK_HASH_HISTORY;
+        match sdk::read_storage(&block_hash_to_key(slot)) {
+            Some(value) if value.len() == 64 && U256::from_big_endian(&value[..32]) == number => {
+                H256::from_slice(&value[32..])
+            }
+            _ => H256::zero(),
+        }
+    }
+
+    /// Returns the index of the block the engine last applied a transaction in,
+    /// or 0 if it never has.
+    pub fn get_last_block_index() -> u64 {
+        sdk::read_u64(LAST_BLOCK_INDEX_KEY).unwrap_or(0)
+    }
+
+    /// Adds to the gas used by the current block, if one has been begun.
+    fn record_block_gas(&mut self, gas_used: u64) {
+        if let Some(block) = self.block.as_mut() {
+            block.gas_used = block.gas_used.saturating_add(gas_used);
+            Self::set_block_state(block);
         }
     }
 
@@ -82,6 +388,42 @@ impl Engine {
         sdk::read_storage(&address_to_key(KeyPrefix::Code, address)).unwrap_or_else(Vec::new)
     }
 
+    /// Returns the code of each of the given addresses, failing if the borsh
+    /// encoding of the result would exceed `max_output_size` bytes.
+    pub fn get_codes(
+        addresses: &[Address],
+        max_output_size: usize,
+    ) -> core::result::Result<Vec<Vec<u8>>, ErrorKind> {
+        // Borsh prefixes the list and each code with a 4-byte length.
+        let mut output_size = 4usize;
+        let mut codes = Vec::with_capacity(addresses.len());
+        for address in addresses {
+            let code = Self::get_code(address);
+            output_size = output_size.saturating_add(4).saturating_add(code.len());
+            if output_size > max_output_size {
+                return Err(ErrorKind::OutputTooLarge);
+            }
+            codes.push(code);
+        }
+        Ok(codes)
+    }
+
+    /// Describes the EVM rules transactions are executed with.
+    pub fn get_evm_config() -> EvmConfigResult {
+        EvmConfigResult::new(HARDFORK, CONFIG)
+    }
+
+    /// Lists the precompiles available to transactions.
+    pub fn get_precompiles() -> Vec<PrecompileInfo> {
+        precompiles::ISTANBUL_PRECOMPILES
+            .iter()
+            .map(|(address, name)| PrecompileInfo {
+                address: Address::from_low_u64_be(*address).0,
+                name: (*name).into(),
+            })
+            .collect()
+    }
+
     pub fn get_code_size(address: &Address) -> usize {
         Engine::get_code(&address).len()
     }
@@ -103,23 +445,180 @@ impl Engine {
             .unwrap_or_else(U256::zero)
     }
 
+    /// Returns the nonce the next transaction of `address` must carry, which
+    /// is the number of transactions it has committed (its stored nonce).
+    pub fn get_next_nonce(address: &Address) -> U256 {
+        Self::get_nonce(address)
+    }
+
+    /// Checks that `nonce` is the one the next transaction of `address` must carry,
+    /// so that signed transactions can neither be replayed nor run out of order.
+    pub fn check_nonce(address: &Address, nonce: &U256) -> bool {
+        *nonce == Self::get_next_nonce(address)
+    }
+
+    /// Counts a transaction of `sender` against the `rate_limit`, failing if
+    /// the sender has used up its current window. Transactions that fail are
+    /// rolled back by NEAR, and so don't count.
+    pub fn check_rate_limit(&self, sender: &Address) -> core::result::Result<(), ErrorKind> {
+        let rate_limit = match self.state.rate_limit {
+            Some(rate_limit) => rate_limit,
+            None => return Ok(()),
+        };
+        let key = address_to_key(KeyPrefix::RateLimit, sender);
+        let window = sdk::block_index() / rate_limit.window_blocks.max(1);
+        // Stored as the window index followed by the transactions in it;
+        // anything else starts a new window.
+        let count = match sdk::read_storage(&key) {
+            Some(value) if value.len() == 16 && value[..8] == window.to_le_bytes() => {
+                let mut count = [0u8; 8];
+                count.copy_from_slice(&value[8..]);
+                u64::from_le_bytes(count)
+            }
+            _ => 0,
+        };
+        if count >= rate_limit.max_transactions {
+            return Err(ErrorKind::RateLimited);
+        }
+        let value = [window.to_le_bytes(), (count + 1).to_le_bytes()].concat();
+        sdk::write_storage(&key, &value);
+        Ok(())
+    }
+
+    /// With the `balance-history` feature, also records the new balance in the
+    /// account's history, see `record_balance_history` for what that costs.
     pub fn set_balance(address: &Address, balance: &U256) {
+        let old_balance = Self::get_balance(address);
+        Self::update_total_balance(&old_balance, balance);
+        #[cfg(feature = "balance-history")]
+        Self::record_balance_history(address, &old_balance, balance);
         sdk::write_storage(
             &address_to_key(KeyPrefix::Balance, address),
             &u256_to_arr(balance),
         );
     }
 
+    /// Records the zero balance in the account's history, like `set_balance`.
     pub fn remove_balance(address: &Address) {
+        let old_balance = Self::get_balance(address);
+        Self::update_total_balance(&old_balance, &U256::zero());
+        #[cfg(feature = "balance-history")]
+        Self::record_balance_history(address, &old_balance, &U256::zero());
         sdk::remove_storage(&address_to_key(KeyPrefix::Balance, address))
     }
 
+    /// Returns the sum of all EVM balances, kept up to date by every balance change.
+    /// Balances which predate the sum are missing from it until
+    /// `recompute_total_balance` is run once, and until then the updates
+    /// saturate at zero rather than underflow when such a balance decreases.
+    pub fn get_total_balance() -> U256 {
+        sdk::read_storage(TOTAL_BALANCE_KEY)
+            .map(|value| U256::from_big_endian(&value))
+            .unwrap_or_else(U256::zero)
+    }
+
+    fn update_total_balance(old_balance: &U256, new_balance: &U256) {
+        if old_balance != new_balance {
+            let total = Self::get_total_balance()
+                .saturating_sub(*old_balance)
+                .saturating_add(*new_balance);
+            sdk::write_storage(TOTAL_BALANCE_KEY, &u256_to_arr(&total));
+        }
+    }
+
+    /// Removes `amount` from the balance of `address`, and so from the total
+    /// supply, returning the remaining balance.
+    pub fn burn(address: &Address, amount: &U256) -> core::result::Result<U256, ErrorKind> {
+        let balance = Self::get_balance(address)
+            .checked_sub(*amount)
+            .ok_or(ErrorKind::InsufficientBalance)?;
+        Self::set_balance(address, &balance);
+        Ok(balance)
+    }
+
+    /// Resets the running total to the sum of the balances of the given addresses,
+    /// which must include every funded account, as contract storage can't be enumerated.
+    pub fn recompute_total_balance(addresses: &[Address]) -> U256 {
+        let mut addresses = addresses.to_vec();
+        addresses.sort();
+        addresses.dedup();
+        let total = addresses.iter().fold(U256::zero(), |total, address| {
+            total.saturating_add(Self::get_balance(address))
+        });
+        sdk::write_storage(TOTAL_BALANCE_KEY, &u256_to_arr(&total));
+        total
+    }
+
     pub fn get_balance(address: &Address) -> U256 {
         sdk::read_storage(&address_to_key(KeyPrefix::Balance, address))
             .map(|value| U256::from_big_endian(&value))
             .unwrap_or_else(U256::zero)
     }
 
+    /// Returns the balance of `address` at the end of the given block, which
+    /// must be one of the last `BALANCE_HISTORY_BLOCKS` blocks.
+    #[cfg(feature = "balance-history")]
+    pub fn get_balance_at(
+        address: &Address,
+        block_height: u64,
+    ) -> core::result::Result<U256, ErrorKind> {
+        let block_index = sdk::block_index();
+        if block_height > block_index
+            || block_height < block_index.saturating_sub(BALANCE_HISTORY_BLOCKS)
+        {
+            return Err(ErrorKind::BlockOutOfRange);
+        }
+        let history = match Self::get_balance_history(address) {
+            Some(history) => history,
+            // The balance hasn't changed since the history is kept:
+            None => return Ok(Self::get_balance(address)),
+        };
+        Ok(history
+            .iter()
+            .rev()
+            .find(|(block, _)| *block <= block_height)
+            .map(|(_, balance)| U256::from_big_endian(balance))
+            .unwrap_or_else(U256::zero))
+    }
+
+    /// The balances of `address` at the end of each block it changed in, oldest
+    /// first, starting with the last change before the retained blocks.
+    #[cfg(feature = "balance-history")]
+    fn get_balance_history(address: &Address) -> Option<Vec<(u64, RawU256)>> {
+        sdk::read_storage(&address_to_key(KeyPrefix::BalanceHistory, address))
+            .map(|bytes| Vec::<(u64, RawU256)>::try_from_slice(&bytes).expect("ERR_DESER"))
+    }
+
+    /// Each change of a balance reads and rewrites the whole history of its
+    /// account, so a transfer does so for both of its accounts. A history holds
+    /// an entry of 40 bytes per block the balance changed in, for up to
+    /// `BALANCE_HISTORY_BLOCKS + 1` blocks: an account changing every block
+    /// keeps about 2.6 KB staked for it, on top of its balance.
+    #[cfg(feature = "balance-history")]
+    fn record_balance_history(address: &Address, old_balance: &U256, new_balance: &U256) {
+        if old_balance == new_balance {
+            return;
+        }
+        let block_index = sdk::block_index();
+        // The first change records the balance it started from, as of genesis:
+        let mut history = Self::get_balance_history(address)
+            .unwrap_or_else(|| vec![(0, u256_to_arr(old_balance))]);
+        match history.last_mut() {
+            Some(entry) if entry.0 == block_index => entry.1 = u256_to_arr(new_balance),
+            _ => history.push((block_index, u256_to_arr(new_balance))),
+        }
+        let first_retained = block_index.saturating_sub(BALANCE_HISTORY_BLOCKS);
+        let base = history
+            .iter()
+            .rposition(|(block, _)| *block <= first_retained)
+            .unwrap_or(0);
+        history.drain(..base);
+        sdk::write_storage(
+            &address_to_key(KeyPrefix::BalanceHistory, address),
+            &history.try_to_vec().expect("ERR_SER"),
+        );
+    }
+
     pub fn remove_storage(address: &Address, key: &H256) {
         sdk::remove_storage(&storage_to_key(address, key));
     }
@@ -161,11 +660,19 @@ impl Engine {
         }
     }
 
-    pub fn transfer(&mut self, _sender: Address, _receiver: Address, _value: U256) -> ExitReason {
-        ExitReason::Fatal(ExitFatal::NotSupported) // TODO: implement balance transfers
+    /// Transfers `value` from `sender` to `receiver` as a call without input,
+    /// so a receiver with code runs its fallback, while a receiver without an
+    /// account becomes a funded account with nonce 0.
+    pub fn transfer(
+        &mut self,
+        sender: Address,
+        receiver: Address,
+        value: U256,
+    ) -> (ExitReason, SubmitResult) {
+        self.call(sender, receiver, value, Vec::new())
     }
 
-    pub fn deploy_code_with_input(&mut self, input: &[u8]) -> (ExitReason, Address) {
+    pub fn deploy_code_with_input(&mut self, input: &[u8]) -> (ExitReason, SubmitResult) {
         let origin = self.origin();
         let value = U256::zero();
         self.deploy_code(origin, value, input)
@@ -176,21 +683,35 @@ impl Engine {
         origin: Address,
         value: U256,
         input: &[u8],
-    ) -> (ExitReason, Address) {
+    ) -> (ExitReason, SubmitResult) {
         let mut executor = self.make_executor();
         let address = executor.create_address(CreateScheme::Legacy { caller: origin });
-        let (status, result) = (
-            executor.transact_create(origin, value, Vec::from(input), u64::max_value()),
-            address,
-        );
+        let status = executor.transact_create(origin, value, Vec::from(input), u64::max_value());
+        let used_gas = executor.used_gas();
         let (values, logs) = executor.into_state().deconstruct();
-        self.apply(values, logs, true);
-        (status, result)
+        let logs: Vec<Log> = logs.into_iter().collect();
+        if let Some(failure) = self.check_logs(&logs, used_gas) {
+            return failure;
+        }
+        self.apply(values, logs.clone(), true);
+        self.record_block_gas(used_gas);
+        let storage = self.storage_counts;
+        (
+            status,
+            submit_result(used_gas, address.0.to_vec(), logs, storage),
+        )
     }
 
-    pub fn call_with_args(&mut self, args: FunctionCallArgs) -> (ExitReason, Vec<u8>) {
+    pub fn call_with_args(&mut self, args: FunctionCallArgs) -> (ExitReason, SubmitResult) {
         let origin = self.origin();
         let contract = Address(args.contract);
+        if self.state.reject_call_without_code && Self::get_code_size(&contract) == 0 {
+            let status = ExitReason::Error(ExitError::Other(Borrowed("ERR_NO_CODE_AT_TARGET")));
+            return (
+                status,
+                submit_result(0, Vec::new(), Vec::new(), StorageCounts::default()),
+            );
+        }
         let value = U256::zero();
         self.call(origin, contract, value, args.input)
     }
@@ -201,20 +722,42 @@ impl Engine {
         contract: Address,
         value: U256,
         input: Vec<u8>,
-    ) -> (ExitReason, Vec<u8>) {
+    ) -> (ExitReason, SubmitResult) {
+        if self.state.reject_send_to_zero && contract == Address::zero() {
+            let status = ExitReason::Error(ExitError::Other(Borrowed("ERR_SEND_TO_ZERO")));
+            return (
+                status,
+                submit_result(0, Vec::new(), Vec::new(), StorageCounts::default()),
+            );
+        }
         let mut executor = self.make_executor();
         let (status, result) =
             executor.transact_call(origin, contract, value, input, u64::max_value());
+        let used_gas = executor.used_gas();
         let (values, logs) = executor.into_state().deconstruct();
-        self.apply(values, logs, true);
-        (status, result)
+        let logs: Vec<Log> = logs.into_iter().collect();
+        if let Some(failure) = self.check_logs(&logs, used_gas) {
+            return failure;
+        }
+        if let Some(failure) = self.check_result_size(&result, used_gas) {
+            return failure;
+        }
+        self.apply(values, logs.clone(), true);
+        self.record_block_gas(used_gas);
+        let storage = self.storage_counts;
+        (status, submit_result(used_gas, result, logs, storage))
     }
 
-    pub fn view_with_args(&self, args: ViewCallArgs) -> (ExitReason, Vec<u8>) {
+    pub fn view_with_args(&self, args: ViewCallArgs) -> TransactionStatus {
         let origin = Address::from_slice(&args.sender);
         let contract = Address::from_slice(&args.address);
         let value = U256::from_big_endian(&args.amount);
-        self.view(origin, contract, value, args.input)
+        let (status, result) = self.view(origin, contract, value, args.input);
+        if result.len() as u64 > self.state.max_return_size {
+            let status = ExitReason::Error(ExitError::Other(Borrowed("ERR_RESULT_TOO_LARGE")));
+            return TransactionStatus::Failed(status);
+        }
+        TransactionStatus::new(status, result)
     }
 
     pub fn view(
@@ -228,13 +771,61 @@ impl Engine {
         executor.transact_call(origin, contract, value, input, u64::max_value())
     }
 
+    /// Fails a transaction which emitted more logs than `max_logs_per_transaction`
+    /// without applying it, so that its result stays within NEAR's return data limit.
+    fn check_logs(&self, logs: &[Log], used_gas: u64) -> Option<(ExitReason, SubmitResult)> {
+        if logs.len() as u64 <= self.state.max_logs_per_transaction {
+            return None;
+        }
+        let status = ExitReason::Error(ExitError::Other(Borrowed("ERR_TOO_MANY_LOGS")));
+        Some((
+            status,
+            submit_result(used_gas, Vec::new(), Vec::new(), StorageCounts::default()),
+        ))
+    }
+
+    /// Fails a transaction which returned more than `max_return_size` bytes
+    /// without applying it, rather than letting NEAR abort on its output.
+    fn check_result_size(
+        &self,
+        result: &[u8],
+        used_gas: u64,
+    ) -> Option<(ExitReason, SubmitResult)> {
+        if result.len() as u64 <= self.state.max_return_size {
+            return None;
+        }
+        let status = ExitReason::Error(ExitError::Other(Borrowed("ERR_RESULT_TOO_LARGE")));
+        Some((
+            status,
+            submit_result(used_gas, Vec::new(), Vec::new(), StorageCounts::default()),
+        ))
+    }
+
     fn make_executor(&self) -> StackExecutor<MemoryStackState<Engine>> {
+        self.code_cache.borrow_mut().clear();
         let metadata = StackSubstateMetadata::new(u64::max_value(), &CONFIG);
         let state = MemoryStackState::new(metadata, self);
         StackExecutor::new_with_precompile(state, &CONFIG, precompiles::istanbul_precompiles)
     }
 }
 
+/// Collects the outcome of a transaction executed at the current block.
+fn submit_result(
+    gas_used: u64,
+    result: Vec<u8>,
+    logs: Vec<Log>,
+    storage: StorageCounts,
+) -> SubmitResult {
+    SubmitResult {
+        gas_used,
+        result,
+        logs: logs.into_iter().map(ResultLog::from).collect(),
+        block_height: sdk::block_index(),
+        storage_writes: storage.writes,
+        storage_zero_writes: storage.zero_writes,
+    }
+}
+
 impl evm::backend::Backend for Engine {
     fn gas_price(&self) -> U256 {
         U256::zero()
@@ -244,36 +835,61 @@ impl evm::backend::Backend for Engine {
         self.origin
     }
 
-    fn block_hash(&self, _number: U256) -> H256 {
-        H256::zero() // TODO: https://github.com/near/nearcore/issues/3456
+    fn block_hash(&self, number: U256) -> H256 {
+        match &self.block {
+            Some(block) => Engine::get_block_hash(U256::from(block.number), number),
+            None => H256::zero(), // TODO: https://github.com/near/nearcore/issues/3456
+        }
     }
 
     fn block_number(&self) -> U256 {
-        U256::from(sdk::block_index())
+        match &self.block {
+            Some(block) => U256::from(block.number),
+            None => U256::from(sdk::block_index()),
+        }
     }
 
     fn block_coinbase(&self) -> Address {
-        Address::zero()
+        match &self.block {
+            Some(block) => Address(block.coinbase),
+            None => Address(self.state.fee_recipient),
+        }
     }
 
     fn block_timestamp(&self) -> U256 {
-        U256::from(sdk::block_timestamp())
+        match &self.block {
+            Some(block) => U256::from(block.timestamp),
+            None => U256::from(sdk::block_timestamp()),
+        }
     }
 
     fn block_difficulty(&self) -> U256 {
-        U256::zero()
+        match &self.block {
+            Some(block) => U256::from(block.difficulty),
+            None => U256::zero(),
+        }
     }
 
     fn block_gas_limit(&self) -> U256 {
-        U256::zero() // TODO
+        match &self.block {
+            Some(block) => U256::from(block.gas_limit),
+            None => U256::zero(), // TODO
+        }
     }
 
     fn chain_id(&self) -> U256 {
         U256::from(self.state.chain_id)
     }
 
+    /// An account exists unless it is empty in the EIP-161 sense (zero balance,
+    /// zero nonce and no code). EXTCODEHASH relies on this: it yields zero for
+    /// accounts that do not exist and the hash of the empty code for existing
+    /// accounts without code (EIP-1052).
     fn exists(&self, address: Address) -> bool {
-        !Engine::is_account_empty(&address)
+        let has_code = !self.code_cache.borrow_mut().get(&address).is_empty();
+        has_code
+            || !Engine::get_nonce(&address).is_zero()
+            || !Engine::get_balance(&address).is_zero()
     }
 
     fn basic(&self, address: Address) -> Basic {
@@ -283,8 +899,10 @@ impl evm::backend::Backend for Engine {
         }
     }
 
+    /// `Backend` hands out owned codes, so the cached code is still copied:
+    /// the cache saves the storage read, not the copy.
     fn code(&self, address: Address) -> Vec<u8> {
-        Engine::get_code(&address)
+        self.code_cache.borrow_mut().get(&address).to_vec()
     }
 
     fn storage(&self, address: Address, index: H256) -> H256 {
@@ -303,6 +921,7 @@ impl ApplyBackend for Engine {
         I: IntoIterator<Item = (H256, H256)>,
         L: IntoIterator<Item = Log>,
     {
+        self.storage_counts = StorageCounts::default();
         for apply in values {
             match apply {
                 Apply::Modify {
@@ -324,9 +943,11 @@ impl ApplyBackend for Engine {
 
                     for (index, value) in storage {
                         if value == H256::default() {
-                            Engine::remove_storage(&address, &index)
+                            Engine::remove_storage(&address, &index);
+                            self.storage_counts.zero_writes += 1;
                         } else {
-                            Engine::set_storage(&address, &index, &value)
+                            Engine::set_storage(&address, &index, &value);
+                            self.storage_counts.writes += 1;
                         }
                     }
 
@@ -341,8 +962,720 @@ impl ApplyBackend for Engine {
         for log in logs {
             sdk::log_utf8(&bytes_to_hex(&log_to_bytes(log)).into_bytes())
         }
+
+        sdk::write_storage(LAST_BLOCK_INDEX_KEY, &sdk::block_index().to_le_bytes());
     }
 }
 
-#[cfg(test)]
-mod tests {}
+#[cfg(all(test, feature = "standalone"))]
+mod tests {
+    use super::*;
+    use crate::standalone::{self, Env};
+    use evm::ExitSucceed;
+
+    /// Init code deploying a contract which emits two empty logs:
+    /// `PUSH1 0 PUSH1 0 LOG0 PUSH1 0 PUSH1 0 LOG0 STOP`.
+    const TWO_LOGS_CONTRACT: &str = "600b600c600039600b6000f360006000a060006000a000";
+
+    /// Init code deploying a contract which returns 42:
+    /// `PUSH1 42 PUSH1 0 MSTORE PUSH1 32 PUSH1 0 RETURN`.
+    const ANSWER_CONTRACT: &str = "600a600c600039600a6000f3602a60005260206000f3";
+
+    fn setup() -> Engine {
+        standalone::set_env(Env {
+            current_account_id: b"aurora".to_vec(),
+            predecessor_account_id: b"alice.near".to_vec(),
+            block_index: 7,
+            ..Env::default()
+        });
+        Engine::set_state(EngineState {
+            owner_id: "aurora".into(),
+            max_logs_per_transaction: 1,
+            max_return_size: 32,
+            ..EngineState::default()
+        });
+        Engine::new(Address::zero())
+    }
+
+    #[test]
+    fn test_state_roundtrip() {
+        setup();
+        assert_eq!(Engine::get_state().owner_id, "aurora");
+    }
+
+    #[test]
+    fn test_legacy_state() {
+        setup();
+        let legacy = LegacyNewCallArgs {
+            chain_id: u256_to_arr(&U256::from(1313161555)),
+            owner_id: "owner.near".into(),
+            bridge_prover_id: "prover.near".into(),
+            upgrade_delay_blocks: 3,
+        };
+        sdk::write_storage(STATE_KEY, &legacy.try_to_vec().unwrap());
+        let state = Engine::get_state();
+        assert_eq!(state.owner_id, "owner.near");
+        assert_eq!(state.upgrade_delay_blocks, 3);
+        assert_eq!(
+            state.max_logs_per_transaction,
+            crate::parameters::DEFAULT_MAX_LOGS_PER_TRANSACTION
+        );
+        assert_eq!(
+            state.max_return_size,
+            crate::parameters::MAX_RETURN_DATA_SIZE
+        );
+
+        Engine::set_state(state);
+        assert_eq!(sdk::read_storage(STATE_KEY).unwrap()[0], STATE_VERSION);
+        let state = Engine::get_state();
+        assert_eq!(U256::from(state.chain_id), U256::from(1313161555));
+        assert_eq!(state.bridge_prover_id, "prover.near");
+    }
+
+    #[test]
+    fn test_is_initialized() {
+        standalone::set_env(Env::default());
+        assert!(!Engine::get_state().is_initialized());
+        setup();
+        assert!(Engine::get_state().is_initialized());
+    }
+
+    #[test]
+    fn test_get_evm_config() {
+        let config = Engine::get_evm_config();
+        assert_eq!(config.hardfork, "istanbul");
+        assert_eq!(config.create_contract_limit, Some(0x6000));
+        assert_eq!(config.gas_transaction_non_zero_data, 16);
+        assert!(config.has_chain_id && config.has_self_balance);
+        assert!(!config.has_access_lists);
+    }
+
+    #[test]
+    fn test_deploy_and_call() {
+        let mut engine = setup();
+        let origin = Address([1u8; 20]);
+        let init_code = hex::decode(ANSWER_CONTRACT).unwrap();
+
+        let (status, result) = engine.deploy_code(origin, U256::zero(), &init_code);
+        assert_eq!(status, ExitReason::Succeed(ExitSucceed::Returned));
+        assert_eq!(result.block_height, 7);
+        let contract = Address::from_slice(&result.result);
+        assert_eq!(Engine::get_code(&contract), init_code[12..].to_vec());
+
+        let (status, result) = engine.call(origin, contract, U256::zero(), Vec::new());
+        assert_eq!(status, ExitReason::Succeed(ExitSucceed::Returned));
+        assert_eq!(U256::from_big_endian(&result.result), U256::from(42));
+        assert!(result.gas_used > 0);
+    }
+
+    #[test]
+    fn test_deploy_returns_create_address() {
+        let mut engine = setup();
+        let origin = Address([1u8; 20]);
+        let init_code = hex::decode(ANSWER_CONTRACT).unwrap();
+        // The CREATE address: keccak256(rlp([sender, nonce]))[12..].
+        let create_address = |nonce: u64| {
+            let mut stream = rlp::RlpStream::new_list(2);
+            stream.append(&origin);
+            stream.append(&nonce);
+            Address::from_slice(&crate::types::keccak(stream.as_raw())[12..])
+        };
+
+        // A transfer and a call use up nonces too:
+        engine.transfer(origin, Address([2u8; 20]), U256::zero());
+        engine.call(origin, Address([2u8; 20]), U256::zero(), vec![1]);
+        let (_, result) = engine.deploy_code(origin, U256::zero(), &init_code);
+        assert_eq!(Address::from_slice(&result.result), create_address(2));
+        let (_, result) = engine.deploy_code(origin, U256::zero(), &init_code);
+        assert_eq!(Address::from_slice(&result.result), create_address(3));
+    }
+
+    #[test]
+    fn test_view_does_not_write() {
+        let mut engine = setup();
+        let origin = Address([1u8; 20]);
+        let init_code = hex::decode(ANSWER_CONTRACT).unwrap();
+        let (_, result) = engine.deploy_code(origin, U256::zero(), &init_code);
+        let contract = Address::from_slice(&result.result);
+        let nonce = Engine::get_nonce(&origin);
+
+        let (status, result) = engine.view(origin, contract, U256::zero(), Vec::new());
+        assert_eq!(status, ExitReason::Succeed(ExitSucceed::Returned));
+        assert_eq!(U256::from_big_endian(&result), U256::from(42));
+        assert_eq!(Engine::get_nonce(&origin), nonce);
+    }
+
+    #[test]
+    fn test_too_many_logs() {
+        let mut engine = setup();
+        let origin = Address([1u8; 20]);
+        let init_code = hex::decode(TWO_LOGS_CONTRACT).unwrap();
+        let (_, result) = engine.deploy_code(origin, U256::zero(), &init_code);
+        let contract = Address::from_slice(&result.result);
+        let nonce = Engine::get_nonce(&origin);
+
+        let (status, result) = engine.call(origin, contract, U256::zero(), Vec::new());
+        assert_eq!(
+            status,
+            ExitReason::Error(ExitError::Other(Borrowed("ERR_TOO_MANY_LOGS")))
+        );
+        assert!(result.logs.is_empty());
+        assert_eq!(Engine::get_nonce(&origin), nonce);
+
+        let mut engine = Engine::new_with_state(
+            EngineState {
+                max_logs_per_transaction: 2,
+                ..Engine::get_state()
+            },
+            origin,
+        );
+        let (status, result) = engine.call(origin, contract, U256::zero(), Vec::new());
+        assert_eq!(status, ExitReason::Succeed(ExitSucceed::Stopped));
+        assert_eq!(result.logs.len(), 2);
+    }
+
+    #[test]
+    fn test_view_status() {
+        let engine = setup();
+        let contract = Address([3u8; 20]);
+        let view = |code: &str| {
+            Engine::set_code(&contract, &hex::decode(code).unwrap());
+            engine.view_with_args(ViewCallArgs {
+                sender: [1u8; 20],
+                address: contract.0,
+                amount: [0u8; 32],
+                input: Vec::new(),
+            })
+        };
+        assert_eq!(
+            view(&ANSWER_CONTRACT[24..]),
+            TransactionStatus::Succeed(u256_to_arr(&U256::from(42)).to_vec())
+        );
+        // `PUSH1 42 PUSH1 0 MSTORE PUSH1 32 PUSH1 0 REVERT`
+        assert_eq!(
+            view("602a60005260206000fd"),
+            TransactionStatus::Revert(u256_to_arr(&U256::from(42)).to_vec())
+        );
+        // `PUSH32 2**256-1 MLOAD`: the memory expansion costs more than any gas.
+        assert_eq!(
+            view(&format!("7f{}51", "ff".repeat(32))),
+            TransactionStatus::OutOfGas
+        );
+    }
+
+    #[test]
+    fn test_send_to_zero() {
+        let mut engine = setup();
+        let origin = Address([1u8; 20]);
+        Engine::set_balance(&origin, &U256::from(10));
+        let zero = Address::zero();
+
+        // By default the zero address is an ordinary (code-less) account:
+        let (status, _) = engine.transfer(origin, zero, U256::from(1));
+        assert_eq!(status, ExitReason::Succeed(ExitSucceed::Stopped));
+        let (status, _) = engine.call(origin, zero, U256::zero(), vec![1, 2, 3]);
+        assert_eq!(status, ExitReason::Succeed(ExitSucceed::Stopped));
+        assert_eq!(Engine::get_balance(&zero), U256::from(1));
+
+        let mut engine = Engine::new_with_state(
+            EngineState {
+                reject_send_to_zero: true,
+                ..Engine::get_state()
+            },
+            origin,
+        );
+        let rejected = ExitReason::Error(ExitError::Other(Borrowed("ERR_SEND_TO_ZERO")));
+        let (status, _) = engine.transfer(origin, zero, U256::from(1));
+        assert_eq!(status, rejected);
+        let (status, _) = engine.call(origin, zero, U256::zero(), vec![1, 2, 3]);
+        assert_eq!(status, rejected);
+        assert_eq!(Engine::get_balance(&zero), U256::from(1));
+        assert_eq!(Engine::get_balance(&origin), U256::from(9));
+    }
+
+    #[test]
+    fn test_call_without_code() {
+        let mut engine = setup();
+        let target = Address([3u8; 20]);
+        let args = || FunctionCallArgs {
+            contract: target.0,
+            input: vec![1, 2, 3],
+        };
+        let (status, _) = engine.call_with_args(args());
+        assert_eq!(status, ExitReason::Succeed(ExitSucceed::Stopped));
+
+        let mut engine = Engine::new_with_state(
+            EngineState {
+                reject_call_without_code: true,
+                ..Engine::get_state()
+            },
+            Address::zero(),
+        );
+        let (status, _) = engine.call_with_args(args());
+        assert_eq!(
+            status,
+            ExitReason::Error(ExitError::Other(Borrowed("ERR_NO_CODE_AT_TARGET")))
+        );
+        Engine::set_code(&target, &hex::decode(&ANSWER_CONTRACT[24..]).unwrap());
+        let (status, _) = engine.call_with_args(args());
+        assert_eq!(status, ExitReason::Succeed(ExitSucceed::Returned));
+    }
+
+    #[test]
+    fn test_precompile_call_types() {
+        let mut engine = setup();
+        let origin = Address([1u8; 20]);
+        let contract = Address([3u8; 20]);
+        let deposit: u128 = 1_000_000_000_000_000_000_000_000;
+        standalone::with_env(|env| env.attached_deposit = deposit);
+        // Calls the attached deposit precompile, returning its 16-byte output:
+        // `CALL`, `CALLCODE` and `DELEGATECALL` with all of the remaining gas.
+        let calls = [
+            "601060006000600060006101025af15060106000f3",
+            "601060006000600060006101025af25060106000f3",
+            "60106000600060006101025af45060106000f3",
+        ];
+        for code in calls.iter() {
+            Engine::set_code(&contract, &hex::decode(code).unwrap());
+            let (status, result) = engine.call(origin, contract, U256::zero(), Vec::new());
+            assert_eq!(status, ExitReason::Succeed(ExitSucceed::Returned));
+            assert_eq!(result.result, deposit.to_be_bytes().to_vec(), "{}", code);
+        }
+    }
+
+    #[test]
+    fn test_result_size() {
+        let mut engine = setup();
+        let origin = Address([1u8; 20]);
+        let contract = Address([3u8; 20]);
+        // `PUSH1 33 PUSH1 0 RETURN`: one byte over the limit of `setup`.
+        Engine::set_code(&contract, &hex::decode("60216000f3").unwrap());
+        let too_large = ExitReason::Error(ExitError::Other(Borrowed("ERR_RESULT_TOO_LARGE")));
+
+        let (status, result) = engine.call(origin, contract, U256::zero(), Vec::new());
+        assert_eq!(status, too_large);
+        assert!(result.result.is_empty());
+        let status = engine.view_with_args(ViewCallArgs {
+            sender: origin.0,
+            address: contract.0,
+            amount: [0u8; 32],
+            input: Vec::new(),
+        });
+        assert_eq!(status, TransactionStatus::Failed(too_large));
+    }
+
+    #[test]
+    fn test_get_codes() {
+        setup();
+        let first = Address([1u8; 20]);
+        let second = Address([2u8; 20]);
+        Engine::set_code(&first, &[0x60, 0x00]);
+        let codes = Engine::get_codes(&[first, second], 1024).ok().unwrap();
+        assert_eq!(codes, vec![vec![0x60, 0x00], Vec::new()]);
+        // 4 + (4 + 2) + (4 + 0) bytes once encoded.
+        assert!(Engine::get_codes(&[first, second], 14).is_ok());
+        assert!(matches!(
+            Engine::get_codes(&[first, second], 13),
+            Err(ErrorKind::OutputTooLarge)
+        ));
+    }
+
+    #[test]
+    fn test_transfer_creates_recipient() {
+        let mut engine = setup();
+        let sender = Address([1u8; 20]);
+        let recipient = Address([2u8; 20]);
+        Engine::set_balance(&sender, &U256::from(100));
+        assert!(Engine::is_account_empty(&recipient));
+
+        let (status, _) = engine.transfer(sender, recipient, U256::from(40));
+        assert_eq!(status, ExitReason::Succeed(ExitSucceed::Stopped));
+        assert_eq!(Engine::get_balance(&recipient), U256::from(40));
+        assert_eq!(Engine::get_nonce(&recipient), U256::zero());
+        assert_eq!(Engine::get_balance(&sender), U256::from(60));
+    }
+
+    #[test]
+    fn test_value_to_precompile() {
+        let mut engine = setup();
+        let sender = Address([1u8; 20]);
+        let identity = Address::from_low_u64_be(4);
+        Engine::set_balance(&sender, &U256::from(100));
+
+        // The precompile runs and keeps the value, whether called with or without input.
+        let (status, result) = engine.transfer(sender, identity, U256::from(40));
+        assert_eq!(status, ExitReason::Succeed(ExitSucceed::Returned));
+        assert!(result.result.is_empty());
+        let (status, result) = engine.call(sender, identity, U256::from(2), vec![1, 2, 3]);
+        assert_eq!(status, ExitReason::Succeed(ExitSucceed::Returned));
+        assert_eq!(result.result, vec![1, 2, 3]);
+        assert_eq!(Engine::get_balance(&identity), U256::from(42));
+        assert_eq!(Engine::get_balance(&sender), U256::from(58));
+    }
+
+    #[test]
+    fn test_call_stipend() {
+        let mut engine = setup();
+        let origin = Address([1u8; 20]);
+        let sender = Address([4u8; 20]);
+        Engine::set_balance(&sender, &U256::from(10));
+        // `CALL` with no gas and a value of 1 to `recipient`, returning its success flag.
+        let send_to = |recipient: &str| {
+            let code = format!("6000600060006000600173{}6000f160005260206000f3", recipient);
+            Engine::set_code(&sender, &hex::decode(code).unwrap());
+        };
+
+        // The 2300 gas stipend covers a `LOG0` in the recipient's fallback...
+        Engine::set_code(&Address([5u8; 20]), &hex::decode("60006000a000").unwrap());
+        send_to(&"05".repeat(20));
+        let (status, result) = engine.call(origin, sender, U256::zero(), Vec::new());
+        assert_eq!(status, ExitReason::Succeed(ExitSucceed::Returned));
+        assert_eq!(result.result, u256_to_arr(&U256::one()).to_vec());
+        assert_eq!(result.logs.len(), 1);
+        assert_eq!(Engine::get_balance(&Address([5u8; 20])), U256::one());
+
+        // ...but not an `SSTORE`, so that call fails and keeps its value.
+        Engine::set_code(&Address([6u8; 20]), &hex::decode("600160005500").unwrap());
+        send_to(&"06".repeat(20));
+        let (status, result) = engine.call(origin, sender, U256::zero(), Vec::new());
+        assert_eq!(status, ExitReason::Succeed(ExitSucceed::Returned));
+        assert_eq!(result.result, u256_to_arr(&U256::zero()).to_vec());
+        assert_eq!(Engine::get_balance(&Address([6u8; 20])), U256::zero());
+        assert_eq!(Engine::get_balance(&sender), U256::from(9));
+    }
+
+    #[test]
+    fn test_last_block_index() {
+        let mut engine = setup();
+        assert_eq!(Engine::get_last_block_index(), 0);
+        let origin = Address([1u8; 20]);
+        engine.view(origin, Address([2u8; 20]), U256::zero(), Vec::new());
+        assert_eq!(Engine::get_last_block_index(), 0);
+        engine.call(origin, Address([2u8; 20]), U256::zero(), Vec::new());
+        assert_eq!(Engine::get_last_block_index(), 7);
+    }
+
+    #[test]
+    fn test_storage_counts() {
+        let mut engine = setup();
+        let origin = Address([1u8; 20]);
+        let contract = Address([3u8; 20]);
+        Engine::set_storage(
+            &contract,
+            &H256::from_low_u64_be(1),
+            &H256::from_low_u64_be(1),
+        );
+        // `PUSH1 1 PUSH1 2 SSTORE PUSH1 1 PUSH1 3 SSTORE PUSH1 0 PUSH1 1 SSTORE`
+        Engine::set_code(
+            &contract,
+            &hex::decode("600160025560016003556000600155").unwrap(),
+        );
+
+        let (status, result) = engine.call(origin, contract, U256::zero(), Vec::new());
+        assert_eq!(status, ExitReason::Succeed(ExitSucceed::Stopped));
+        assert_eq!(result.storage_writes, 2);
+        assert_eq!(result.storage_zero_writes, 1);
+    }
+
+    #[test]
+    fn test_total_balance() {
+        let mut engine = setup();
+        let sender = Address([1u8; 20]);
+        let recipient = Address([2u8; 20]);
+        Engine::set_balance(&sender, &U256::from(100));
+        Engine::set_balance(&recipient, &U256::from(5));
+        assert_eq!(Engine::get_total_balance(), U256::from(105));
+
+        engine.transfer(sender, recipient, U256::from(40));
+        assert_eq!(Engine::get_total_balance(), U256::from(105));
+        Engine::remove_account(&recipient);
+        assert_eq!(Engine::get_total_balance(), U256::from(60));
+
+        sdk::write_storage(TOTAL_BALANCE_KEY, &u256_to_arr(&U256::zero()));
+        let total = Engine::recompute_total_balance(&[sender, recipient, sender]);
+        assert_eq!(total, U256::from(60));
+        assert_eq!(Engine::get_total_balance(), U256::from(60));
+    }
+
+    #[cfg(feature = "balance-history")]
+    #[test]
+    fn test_balance_history() {
+        setup();
+        let address = Address([1u8; 20]);
+        let set_block = |block_index| standalone::with_env(|env| env.block_index = block_index);
+        assert_eq!(Engine::get_balance_at(&address, 7).ok(), Some(U256::zero()));
+
+        Engine::set_balance(&address, &U256::from(100));
+        set_block(9);
+        Engine::set_balance(&address, &U256::from(50));
+        Engine::set_balance(&address, &U256::from(40));
+        let balance_at = |block_height| Engine::get_balance_at(&address, block_height).ok();
+        assert_eq!(balance_at(6), Some(U256::zero()));
+        assert_eq!(balance_at(7), Some(U256::from(100)));
+        assert_eq!(balance_at(8), Some(U256::from(100)));
+        assert_eq!(balance_at(9), Some(U256::from(40)));
+        assert_eq!(balance_at(10), None);
+
+        set_block(7 + BALANCE_HISTORY_BLOCKS + 1);
+        assert_eq!(balance_at(7), None);
+        assert_eq!(balance_at(8), Some(U256::from(100)));
+
+        // Older changes are pruned, keeping the one the retained blocks start from:
+        set_block(100);
+        Engine::set_balance(&address, &U256::from(30));
+        assert_eq!(Engine::get_balance_history(&address).unwrap().len(), 2);
+        assert_eq!(
+            balance_at(100 - BALANCE_HISTORY_BLOCKS),
+            Some(U256::from(40))
+        );
+        assert_eq!(balance_at(100), Some(U256::from(30)));
+    }
+
+    #[test]
+    fn test_burn() {
+        setup();
+        let address = Address([1u8; 20]);
+        Engine::set_balance(&address, &U256::from(100));
+        Engine::set_balance(&Address([2u8; 20]), &U256::from(5));
+
+        assert!(matches!(Engine::burn(&address, &U256::from(40)), Ok(b) if b == U256::from(60)));
+        assert_eq!(Engine::get_balance(&address), U256::from(60));
+        assert_eq!(Engine::get_total_balance(), U256::from(65));
+
+        assert!(matches!(
+            Engine::burn(&address, &U256::from(61)),
+            Err(ErrorKind::InsufficientBalance)
+        ));
+        assert_eq!(Engine::get_balance(&address), U256::from(60));
+        assert_eq!(Engine::get_total_balance(), U256::from(65));
+    }
+
+    #[test]
+    fn test_code_size_limit() {
+        let mut engine = setup();
+        let origin = Address([1u8; 20]);
+        // Init code returning 0x6000 (EIP-170's limit) and 0x6001 zero bytes:
+        // `PUSH2 size PUSH1 0 RETURN`.
+        let (status, result) =
+            engine.deploy_code(origin, U256::zero(), &[0x61, 0x60, 0x00, 0x60, 0x00, 0xf3]);
+        assert_eq!(status, ExitReason::Succeed(ExitSucceed::Returned));
+        assert_eq!(
+            Engine::get_code_size(&Address::from_slice(&result.result)),
+            0x6000
+        );
+        let (status, _) =
+            engine.deploy_code(origin, U256::zero(), &[0x61, 0x60, 0x01, 0x60, 0x00, 0xf3]);
+        assert_eq!(status, ExitReason::Error(ExitError::CreateContractLimit));
+    }
+
+    #[test]
+    fn test_create_collision() {
+        let mut engine = setup();
+        let origin = Address([1u8; 20]);
+        let code = hex::decode(ANSWER_CONTRACT).unwrap();
+        let (status, result) = engine.deploy_code(origin, U256::zero(), &code);
+        assert_eq!(status, ExitReason::Succeed(ExitSucceed::Returned));
+        let contract = Address::from_slice(&result.result);
+
+        // Contrived: rewinding the nonce makes the next deployment target the same address.
+        Engine::set_nonce(&origin, &U256::zero());
+        let (status, _) = engine.deploy_code(origin, U256::zero(), &[0x60, 0x00, 0x60, 0x00, 0xf3]);
+        assert_eq!(status, ExitReason::Error(ExitError::CreateCollision));
+        assert_eq!(Engine::get_code(&contract), code[12..].to_vec());
+    }
+
+    #[test]
+    fn test_rate_limit() {
+        let engine = setup();
+        let sender = Address([1u8; 20]);
+        assert!(engine.check_rate_limit(&sender).is_ok());
+
+        let engine = Engine::new_with_state(
+            EngineState {
+                rate_limit: Some(RateLimit {
+                    max_transactions: 2,
+                    window_blocks: 10,
+                }),
+                ..Engine::get_state()
+            },
+            sender,
+        );
+        assert!(engine.check_rate_limit(&sender).is_ok());
+        assert!(engine.check_rate_limit(&sender).is_ok());
+        assert!(matches!(
+            engine.check_rate_limit(&sender),
+            Err(ErrorKind::RateLimited)
+        ));
+        // Other senders have limits of their own, and a new window resets them:
+        assert!(engine.check_rate_limit(&Address([2u8; 20])).is_ok());
+        standalone::with_env(|env| env.block_index = 10);
+        assert!(engine.check_rate_limit(&sender).is_ok());
+
+        // A malformed count doesn't fail the check:
+        let key = address_to_key(KeyPrefix::RateLimit, &sender);
+        let window = 1u64.to_le_bytes();
+        let malformed = [
+            vec![1u8],
+            window.to_vec(),
+            [&window[..], &[0xffu8; 12][..]].concat(),
+        ];
+        for value in malformed.iter() {
+            sdk::write_storage(&key, value);
+            assert!(engine.check_rate_limit(&sender).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_staged_upgrade() {
+        setup();
+        let state = EngineState {
+            upgrade_delay_blocks: 2,
+            ..Engine::get_state()
+        };
+        assert_eq!(Engine::get_upgrade_index(&state), None);
+        assert!(matches!(
+            Engine::check_staged_upgrade(&state),
+            Err(ErrorKind::NoUpgrade)
+        ));
+
+        Engine::stage_upgrade(b"code");
+        assert_eq!(Engine::get_upgrade_index(&state), Some(9));
+        assert!(matches!(
+            Engine::check_staged_upgrade(&state),
+            Err(ErrorKind::UpgradeTooEarly)
+        ));
+        standalone::with_env(|env| env.block_index = 10);
+        assert!(Engine::check_staged_upgrade(&state).is_ok());
+
+        // Code altered or removed since it was staged is never deployed:
+        sdk::write_storage(CODE_KEY, b"other code");
+        assert!(matches!(
+            Engine::check_staged_upgrade(&state),
+            Err(ErrorKind::UpgradeCodeMismatch)
+        ));
+        sdk::remove_storage(CODE_KEY);
+        assert!(matches!(
+            Engine::check_staged_upgrade(&state),
+            Err(ErrorKind::NoUpgrade)
+        ));
+        Engine::stage_upgrade(b"code");
+        Engine::finish_upgrade();
+        assert_eq!(Engine::get_upgrade_index(&state), None);
+    }
+
+    /// A block of the given number, with a hash telling it apart.
+    #[cfg(feature = "evm_bully")]
+    fn block(number: u64) -> BlockState {
+        BlockState {
+            hash: H256::from_low_u64_be(number + 1).0,
+            number: u256_to_arr(&U256::from(number)),
+            ..BlockState::default()
+        }
+    }
+
+    #[cfg(feature = "evm_bully")]
+    #[test]
+    fn test_block_hashes() {
+        setup();
+        for number in 0..=300 {
+            Engine::begin_block(block(number));
+        }
+        let hash_at = |current: u64, number: u64| {
+            Engine::get_block_hash(U256::from(current), U256::from(number))
+        };
+        let hash = |number: u64| H256::from_low_u64_be(number + 1);
+
+        // Only the blocks preceding the current one have a hash:
+        assert_eq!(hash_at(300, 300), H256::zero());
+        assert_eq!(hash_at(300, 301), H256::zero());
+        assert_eq!(hash_at(300, 299), hash(299));
+        // Including the blocks whose slots wrapped around the ring buffer:
+        assert_eq!(hash_at(300, 255), hash(255));
+        assert_eq!(hash_at(300, 256), hash(256));
+        // But only the last `BLOCK_HASH_HISTORY` of them:
+        assert_eq!(hash_at(300, 300 - BLOCK_HASH_HISTORY), hash(44));
+        assert_eq!(hash_at(300, 300 - BLOCK_HASH_HISTORY - 1), H256::zero());
+        // A slot overwritten by a newer block no longer answers for the older one,
+        // as when replaying from an earlier block:
+        assert_eq!(hash_at(44, 43), H256::zero());
+        assert_eq!(hash_at(300, 43 + BLOCK_HASH_HISTORY), hash(299));
+    }
+
+    #[cfg(feature = "evm_bully")]
+    #[test]
+    fn test_block_hashes_skipped_blocks() {
+        setup();
+        Engine::begin_block(block(10));
+        Engine::begin_block(block(265));
+        Engine::begin_block(block(267));
+        // Blocks 10 and 265 are recorded, in slots no later block has taken:
+        assert_eq!(
+            Engine::get_block_hash(U256::from(267), U256::from(265)),
+            H256::from_low_u64_be(266)
+        );
+        assert_eq!(
+            Engine::get_block_hash(U256::from(265), U256::from(10)),
+            H256::from_low_u64_be(11)
+        );
+        // The skipped block 266 shares the slot of block 10, but isn't block 10:
+        assert_eq!(
+            Engine::get_block_hash(U256::from(267), U256::from(266)),
+            H256::zero()
+        );
+        // Beginning a block resets the gas used by the block:
+        let mut engine = Engine::new(Address::zero());
+        engine.call(
+            Address([1u8; 20]),
+            Address([2u8; 20]),
+            U256::zero(),
+            Vec::new(),
+        );
+        assert!(Engine::get_block_state().unwrap().gas_used > 0);
+        Engine::begin_block(block(268));
+        assert_eq!(Engine::get_block_state().unwrap().gas_used, 0);
+    }
+
+    #[test]
+    fn test_next_nonce() {
+        let mut engine = setup();
+        let sender = Address([1u8; 20]);
+        assert_eq!(Engine::get_next_nonce(&sender), U256::zero());
+        assert!(Engine::check_nonce(&sender, &U256::zero()));
+
+        engine.transfer(sender, Address([2u8; 20]), U256::zero());
+        assert_eq!(Engine::get_next_nonce(&sender), U256::one());
+        assert!(!Engine::check_nonce(&sender, &U256::zero()));
+        assert!(Engine::check_nonce(&sender, &U256::one()));
+    }
+
+    #[test]
+    fn test_code_cache() {
+        setup();
+        let mut cache = CodeCache::default();
+        let addresses: Vec<Address> = (0..=CODE_CACHE_SIZE as u64)
+            .map(Address::from_low_u64_be)
+            .collect();
+        for address in &addresses {
+            Engine::set_code(address, &[1]);
+            assert_eq!(cache.get(address), &[1]);
+        }
+        for address in &addresses {
+            Engine::set_code(address, &[2]);
+        }
+        // The most recent codes are served from the cache, the oldest was evicted.
+        assert_eq!(cache.get(&addresses[CODE_CACHE_SIZE]), &[1]);
+        assert_eq!(cache.get(&addresses[0]), &[2]);
+        cache.clear();
+        assert_eq!(cache.get(&addresses[CODE_CACHE_SIZE]), &[2]);
+    }
+
+    #[test]
+    fn test_balance_roundtrip() {
+        setup();
+        let address = Address([2u8; 20]);
+        assert!(Engine::is_account_empty(&address));
+        Engine::set_balance(&address, &U256::from(100));
+        assert_eq!(Engine::get_balance(&address), U256::from(100));
+        assert!(!Engine::is_account_empty(&address));
+    }
+}
@@ -12,36 +12,49 @@ pub mod parameters;
 mod precompiles;
 pub mod prelude;
 mod storage;
-mod transaction;
+pub mod transaction;
 pub mod types;
 
-#[cfg(feature = "contract")]
-mod engine;
+#[cfg(any(feature = "contract", feature = "standalone"))]
+pub mod engine;
 #[cfg(feature = "contract")]
 mod json;
 #[cfg(feature = "contract")]
 mod log_entry;
-#[cfg(feature = "contract")]
+#[cfg(any(feature = "contract", feature = "standalone"))]
 mod sdk;
+#[cfg(feature = "standalone")]
+pub mod standalone;
 
 #[cfg(feature = "contract")]
 mod contract {
-    use borsh::BorshDeserialize;
-    use evm::ExitReason;
-
-    use crate::engine::{Engine, EngineState};
+    use borsh::{BorshDeserialize, BorshSerialize};
+    use evm::{ExitError, ExitReason};
+
+    use crate::engine::{Engine, EngineState, CODE_KEY};
+    #[cfg(feature = "integration-test")]
+    use crate::parameters::AccountBalance;
+    #[cfg(feature = "balance-history")]
+    use crate::parameters::GetBalanceAtArgs;
     #[cfg(feature = "evm_bully")]
     use crate::parameters::{BeginBlockArgs, BeginChainArgs};
-    use crate::parameters::{FunctionCallArgs, GetStorageAtArgs, NewCallArgs, ViewCallArgs};
-    use crate::prelude::{vec, Address, H256, U256};
+    use crate::parameters::{
+        BurnArgs, ComputeStructHashArgs, FunctionCallArgs, GetCodesArgs, GetStorageAtArgs,
+        GetStorageSlotsArgs, LogLevel, NewCallArgs, RecomputeTotalBalanceArgs, SetChainIdArgs,
+        SubmitResult, TransactionStatus, ViewCallArgs, MAX_RETURN_DATA_SIZE,
+    };
+    use crate::prelude::{Address, String, ToString, Vec, H256, U256};
     use crate::sdk;
-    use crate::types::{near_account_to_evm_address, u256_to_arr};
+    use crate::types::{bytes_to_hex, is_valid_account_id, u256_to_arr, ErrorKind, RawH256};
 
     #[global_allocator]
     static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
 
-    const CODE_KEY: &[u8; 5] = b"\0CODE";
-    const CODE_STAGE_KEY: &[u8; 11] = b"\0CODE_STAGE";
+    /// Gas attached to the `finish_upgrade` callback of `deploy_upgrade`.
+    const FINISH_UPGRADE_GAS: u64 = 10_000_000_000_000;
+
+    /// Maximum number of storage slots `get_storage_slots` reads in one call.
+    const MAX_STORAGE_SLOTS: usize = 256;
 
     #[cfg(target_arch = "wasm32")]
     #[panic_handler]
@@ -66,13 +79,36 @@ mod contract {
     #[no_mangle]
     pub extern "C" fn new() {
         let state = Engine::get_state();
-        if !state.owner_id.is_empty() {
-            require_owner_only(state);
+        let initialized = state.is_initialized();
+        if initialized {
+            require_owner_only(&state);
+        }
+        let args = match NewCallArgs::try_from_slice_compatible(&sdk::read_input()) {
+            Ok(args) => args,
+            Err(_) => sdk::panic_utf8(b"ERR_PARSE_NEW_ARGS"),
+        };
+        match args.validate() {
+            Ok(()) => {}
+            Err(ErrorKind::InvalidMaxReturnSize) => sdk::panic_utf8(b"ERR_INVALID_MAX_RETURN_SIZE"),
+            Err(ErrorKind::InvalidMaxLogsPerTransaction) => {
+                sdk::panic_utf8(b"ERR_INVALID_MAX_LOGS_PER_TRANSACTION")
+            }
+            Err(_) => sdk::panic_utf8(b"ERR_INVALID_NEW_ARGS"),
+        }
+        // Changing the scheme would orphan the accounts derived with the old one:
+        if initialized && args.derivation_scheme != state.derivation_scheme {
+            sdk::panic_utf8(b"ERR_DERIVATION_SCHEME_CHANGE");
         }
-        let args = NewCallArgs::try_from_slice(&sdk::read_input()).expect("ERR_ARG_PARSE");
         Engine::set_state(args.into());
     }
 
+    /// Returns whether `new` has been called, as a single 0 or 1 byte.
+    #[no_mangle]
+    pub extern "C" fn is_initialized() {
+        let initialized = Engine::get_state().is_initialized();
+        sdk::return_output(&[initialized as u8])
+    }
+
     /// Get version of the contract.
     #[no_mangle]
     pub extern "C" fn get_version() {
@@ -97,37 +133,160 @@ mod contract {
         sdk::return_output(state.bridge_prover_id.as_bytes());
     }
 
+    /// Replace the bridge prover, keeping the rest of the configuration.
+    #[no_mangle]
+    pub extern "C" fn set_bridge_prover() {
+        let mut state = Engine::get_state();
+        require_owner_only(&state);
+        let bridge_prover_id = sdk::read_input();
+        if !is_valid_account_id(&bridge_prover_id) {
+            sdk::panic_utf8(b"ERR_INVALID_ACCOUNT_ID");
+        }
+        state.bridge_prover_id =
+            String::from_utf8(bridge_prover_id).expect("ERR_INVALID_ACCOUNT_ID");
+        Engine::set_state(state);
+    }
+
+    /// Change the chain id. This invalidates every pending transaction signed
+    /// for the old chain id, and every meta-call signature, as the chain id is
+    /// part of their EIP-712 domain, so it must be explicitly confirmed.
+    #[no_mangle]
+    pub extern "C" fn set_chain_id() {
+        let mut state = Engine::get_state();
+        require_owner_only(&state);
+        let input = sdk::read_input();
+        let args = SetChainIdArgs::try_from_slice(&input).expect("ERR_PARSE_SET_CHAIN_ID_ARGS");
+        if !args.confirm {
+            sdk::panic_utf8(b"ERR_CHAIN_ID_CHANGE_UNCONFIRMED");
+        }
+        // Logged whatever the log level, as clients must learn of the change:
+        sdk::log(
+            [
+                "WARNING: chain id changed from ",
+                &U256::from(state.chain_id).to_string(),
+                " to ",
+                &U256::from(args.chain_id).to_string(),
+                ", invalidating pending signed transactions and meta-calls",
+            ]
+            .concat(),
+        );
+        state.chain_id = args.chain_id;
+        Engine::set_state(state);
+    }
+
+    /// Recompute the sum of all EVM balances from the balances of the given
+    /// addresses, for audits and for balances set before the sum was kept.
+    #[no_mangle]
+    pub extern "C" fn recompute_total_evm_balance() {
+        let state = Engine::get_state();
+        require_owner_only(&state);
+        let input = sdk::read_input();
+        let args = RecomputeTotalBalanceArgs::try_from_slice(&input)
+            .expect("ERR_PARSE_RECOMPUTE_TOTAL_BALANCE_ARGS");
+        let addresses: Vec<Address> = args.addresses.into_iter().map(Address).collect();
+        let total = Engine::recompute_total_balance(&addresses);
+        sdk::return_output(&u256_to_arr(&total))
+    }
+
+    /// Burn EVM balance: remove an amount from an address, reducing the supply.
+    #[no_mangle]
+    pub extern "C" fn burn() {
+        let state = Engine::get_state();
+        require_owner_only(&state);
+        let input = sdk::read_input();
+        let args = BurnArgs::try_from_slice(&input).expect("ERR_PARSE_BURN_ARGS");
+        let address = Address(args.address);
+        let amount = U256::from(args.amount);
+        let balance = match Engine::burn(&address, &amount) {
+            Ok(balance) => balance,
+            Err(_) => sdk::panic_utf8(b"ERR_INSUFFICIENT_BALANCE"),
+        };
+        crate::log!(
+            state,
+            LogLevel::Info,
+            [
+                "Burned ",
+                &amount.to_string(),
+                " from 0x",
+                &bytes_to_hex(address.as_bytes())
+            ]
+            .concat()
+        );
+        sdk::return_output(&u256_to_arr(&balance))
+    }
+
     /// Get chain id for this contract.
     #[no_mangle]
     pub extern "C" fn get_chain_id() {
         sdk::return_output(&Engine::get_state().chain_id)
     }
 
+    /// Get the index of the block a transaction was last processed in.
+    #[no_mangle]
+    pub extern "C" fn get_last_block_index() {
+        sdk::return_output(&Engine::get_last_block_index().to_le_bytes())
+    }
+
     #[no_mangle]
     pub extern "C" fn get_upgrade_index() {
         let state = Engine::get_state();
-        let index = sdk::read_u64(CODE_STAGE_KEY).expect("ERR_NO_UPGRADE");
-        sdk::return_output(&(index + state.upgrade_delay_blocks).to_le_bytes())
+        match Engine::get_upgrade_index(&state) {
+            Some(index) => sdk::return_output(&index.to_le_bytes()),
+            None => sdk::panic_utf8(b"ERR_NO_UPGRADE"),
+        }
     }
 
     /// Stage new code for deployment.
     #[no_mangle]
     pub extern "C" fn stage_upgrade() {
         let state = Engine::get_state();
-        require_owner_only(state);
-        sdk::read_input_and_store(CODE_KEY);
-        sdk::write_storage(CODE_STAGE_KEY, &sdk::block_index().to_le_bytes());
+        require_owner_only(&state);
+        let code = sdk::read_input();
+        if code.is_empty() {
+            sdk::panic_utf8(b"ERR_EMPTY_UPGRADE_CODE");
+        }
+        Engine::stage_upgrade(&code);
     }
 
     /// Deploy staged upgrade.
+    /// The staged code is checked against the hash recorded when staging it,
+    /// so that a missing or altered code is never deployed.
     #[no_mangle]
     pub extern "C" fn deploy_upgrade() {
         let state = Engine::get_state();
-        let index = sdk::read_u64(CODE_STAGE_KEY).unwrap();
-        if sdk::block_index() <= index + state.upgrade_delay_blocks {
-            sdk::panic_utf8(b"ERR_NOT_ALLOWED:TOO_EARLY");
+        match Engine::check_staged_upgrade(&state) {
+            Ok(()) => {}
+            Err(ErrorKind::UpgradeTooEarly) => sdk::panic_utf8(b"ERR_NOT_ALLOWED:TOO_EARLY"),
+            Err(ErrorKind::UpgradeCodeMismatch) => sdk::panic_utf8(b"ERR_UPGRADE_CODE_MISMATCH"),
+            Err(_) => sdk::panic_utf8(b"ERR_NO_UPGRADE"),
+        }
+        let promise_id = sdk::self_deploy(CODE_KEY);
+        // The callback runs the deployed code, which must keep `finish_upgrade`:
+        let account_id =
+            String::from_utf8(sdk::current_account_id()).expect("ERR_INVALID_ACCOUNT_ID");
+        let callback = sdk::promise_then(
+            promise_id,
+            account_id,
+            b"finish_upgrade",
+            &[],
+            0,
+            FINISH_UPGRADE_GAS,
+        );
+        sdk::promise_return(callback);
+    }
+
+    /// Callback of `deploy_upgrade`: clears the staged upgrade once its
+    /// deployment succeeded, and keeps it in place otherwise.
+    #[no_mangle]
+    pub extern "C" fn finish_upgrade() {
+        if sdk::predecessor_account_id() != sdk::current_account_id() {
+            sdk::panic_utf8(b"ERR_NOT_ALLOWED");
+        }
+        if sdk::promise_results_count() == 1 {
+            if let sdk::PromiseResult::Successful(_) = sdk::promise_result(0) {
+                Engine::finish_upgrade();
+            }
         }
-        sdk::self_deploy(CODE_KEY);
     }
 
     ///
@@ -138,21 +297,30 @@ mod contract {
     #[no_mangle]
     pub extern "C" fn deploy_code() {
         let input = sdk::read_input();
-        let mut engine = Engine::new(predecessor_address());
-        let (status, address) = Engine::deploy_code_with_input(&mut engine, &input);
+        if input.is_empty() {
+            sdk::panic_utf8(b"ERR_EMPTY_DEPLOY_CODE");
+        }
+        let state = Engine::get_state();
+        let origin = predecessor_address(&state);
+        let mut engine = Engine::new_with_state(state, origin);
+        require_within_rate_limit(&engine, &origin);
+        let (status, result) = Engine::deploy_code_with_input(&mut engine, &input);
         // TODO: charge for storage
-        process_exit_reason(status, &address.0)
+        process_submit_result(status, result)
     }
 
     /// Call method on the EVM contract.
     #[no_mangle]
     pub extern "C" fn call() {
         let input = sdk::read_input();
-        let args = FunctionCallArgs::try_from_slice(&input).expect("ERR_ARG_PARSE");
-        let mut engine = Engine::new(predecessor_address());
+        let args = FunctionCallArgs::try_from_slice(&input).expect("ERR_PARSE_FUNCTION_CALL_ARGS");
+        let state = Engine::get_state();
+        let origin = predecessor_address(&state);
+        let mut engine = Engine::new_with_state(state, origin);
+        require_within_rate_limit(&engine, &origin);
         let (status, result) = Engine::call_with_args(&mut engine, args);
         // TODO: charge for storage
-        process_exit_reason(status, &result)
+        process_submit_result(status, result)
     }
 
     /// Process signed Ethereum transaction.
@@ -163,17 +331,40 @@ mod contract {
         use rlp::{Decodable, Rlp};
 
         let input = sdk::read_input();
-        let signed_transaction = EthSignedTransaction::decode(&Rlp::new(&input))
-            .map_err(|_| ())
-            .expect("ERR_INVALID_TX");
+        let signed_transaction = match EthSignedTransaction::decode(&Rlp::new(&input)) {
+            Ok(signed_transaction) => signed_transaction,
+            Err(_) => sdk::panic_utf8(b"ERR_INVALID_TX"),
+        };
 
         let state = Engine::get_state();
 
-        // Validate the chain ID, if provided inside the signature:
-        if let Some(chain_id) = signed_transaction.chain_id() {
-            if U256::from(chain_id) != U256::from(state.chain_id) {
-                sdk::panic_utf8(b"ERR_INVALID_CHAIN_ID");
+        // Validate the chain ID, if provided inside the signature; it may be required:
+        match signed_transaction.chain_id() {
+            Some(chain_id) if U256::from(chain_id) != U256::from(state.chain_id) => {
+                sdk::panic_utf8(b"ERR_INVALID_CHAIN_ID")
             }
+            None if state.require_chain_id => sdk::panic_utf8(b"ERR_CHAIN_ID_REQUIRED"),
+            _ => {}
+        }
+
+        // Validate that the gas price is at least the configured floor:
+        if signed_transaction.transaction.gas_price < U256::from(state.min_gas_price) {
+            sdk::panic_utf8(b"ERR_GAS_PRICE_TOO_LOW");
+        }
+
+        // Validate that the gas limit covers the intrinsic gas (EIP-2028 aware):
+        match signed_transaction
+            .transaction
+            .intrinsic_gas(crate::engine::CONFIG)
+        {
+            Some(intrinsic_gas)
+                if U256::from(intrinsic_gas) <= signed_transaction.transaction.gas => {}
+            _ => sdk::panic_utf8(b"ERR_INTRINSIC_GAS"),
+        }
+
+        // Validate that the signature is in its canonical, low-s form (EIP-2):
+        if !signed_transaction.has_low_s() {
+            sdk::panic_utf8(b"ERR_INVALID_S_VALUE");
         }
 
         // Retrieve the signer of the transaction:
@@ -182,28 +373,31 @@ mod contract {
             None => sdk::panic_utf8(b"ERR_INVALID_ECDSA_SIGNATURE"),
         };
 
+        // Validate that the nonce is the sender's next one:
+        if !Engine::check_nonce(&sender, &signed_transaction.transaction.nonce) {
+            sdk::panic_utf8(b"ERR_INCORRECT_NONCE");
+        }
+
         // Figure out what kind of a transaction this is, and execute it:
         let mut engine = Engine::new_with_state(state, sender);
+        require_within_rate_limit(&engine, &sender);
         let value = signed_transaction.transaction.value;
         let data = signed_transaction.transaction.data;
         if let Some(receiver) = signed_transaction.transaction.to {
             let (status, result) = if data.is_empty() {
                 // Execute a balance transfer:
-                (
-                    Engine::transfer(&mut engine, sender, receiver, value),
-                    vec![],
-                )
+                Engine::transfer(&mut engine, sender, receiver, value)
             } else {
                 // Execute a contract call:
                 Engine::call(&mut engine, sender, receiver, value, data)
                 // TODO: charge for storage
             };
-            process_exit_reason(status, &result)
+            process_submit_result(status, result)
         } else {
             // Execute a contract deployment:
             let (status, result) = Engine::deploy_code(&mut engine, sender, value, &data);
             // TODO: charge for storage
-            process_exit_reason(status, &result.0)
+            process_submit_result(status, result)
         }
     }
 
@@ -218,18 +412,26 @@ mod contract {
             input,
         ) {
             Ok(args) => args,
+            Err(ErrorKind::MetaTransactionTooNested) => {
+                sdk::panic_utf8(b"ERR_META_TX_TOO_NESTED");
+            }
             Err(_error_kind) => {
                 sdk::panic_utf8(b"ERR_META_TX_PARSE");
             }
         };
+        // The signed nonce must be the sender's next one:
+        if !Engine::check_nonce(&meta_call_args.sender, &meta_call_args.nonce) {
+            sdk::panic_utf8(b"ERR_INCORRECT_NONCE");
+        }
         let mut engine = Engine::new_with_state(state, meta_call_args.sender);
+        require_within_rate_limit(&engine, &meta_call_args.sender);
         let (status, result) = engine.call(
             meta_call_args.sender,
             meta_call_args.contract_address,
             meta_call_args.value,
             meta_call_args.input,
         );
-        process_exit_reason(status, &result);
+        process_submit_result(status, result);
     }
 
     ///
@@ -239,10 +441,19 @@ mod contract {
     #[no_mangle]
     pub extern "C" fn view() {
         let input = sdk::read_input();
-        let args = ViewCallArgs::try_from_slice(&input).expect("ERR_ARG_PARSE");
-        let engine = Engine::new(Address::from_slice(&args.sender));
-        let (status, result) = Engine::view_with_args(&engine, args);
-        process_exit_reason(status, &result)
+        let state = Engine::get_state();
+        let args = match ViewCallArgs::try_from_slice_bounded(&input, state.max_view_input_size) {
+            Ok(args) => args,
+            Err(ErrorKind::ViewInputTooLarge) => sdk::panic_utf8(b"ERR_VIEW_INPUT_TOO_LARGE"),
+            Err(_) => sdk::panic_utf8(b"ERR_PARSE_VIEW_ARGS"),
+        };
+        let engine = Engine::new_with_state(state, Address::from_slice(&args.sender));
+        match Engine::view_with_args(&engine, args) {
+            TransactionStatus::Succeed(output) => sdk::return_output(&output),
+            TransactionStatus::Revert(data) => sdk::panic_hex(&data),
+            TransactionStatus::OutOfGas => sdk::panic_utf8(b"ERR_OUT_OF_GAS"),
+            TransactionStatus::Failed(status) => process_exit_reason(status, &[]),
+        }
     }
 
     #[no_mangle]
@@ -259,6 +470,19 @@ mod contract {
         sdk::return_output(&u256_to_arr(&balance))
     }
 
+    /// Returns the balance of an address as of one of the most recent blocks.
+    #[cfg(feature = "balance-history")]
+    #[no_mangle]
+    pub extern "C" fn get_balance_at() {
+        let input = sdk::read_input();
+        let args = GetBalanceAtArgs::try_from_slice(&input).expect("ERR_PARSE_GET_BALANCE_AT_ARGS");
+        match Engine::get_balance_at(&Address(args.address), args.block_height) {
+            Ok(balance) => sdk::return_output(&u256_to_arr(&balance)),
+            Err(_) => sdk::panic_utf8(b"ERR_BLOCK_OUT_OF_RANGE"),
+        }
+    }
+
+    /// Returns the number of transactions the address has committed.
     #[no_mangle]
     pub extern "C" fn get_nonce() {
         let address = sdk::read_input_arr20();
@@ -266,14 +490,114 @@ mod contract {
         sdk::return_output(&u256_to_arr(&nonce))
     }
 
+    /// Returns the nonce the sender's next `raw_call` or `meta_call` must carry.
+    /// It reflects all committed transactions, as each call runs to completion.
+    #[no_mangle]
+    pub extern "C" fn get_next_nonce() {
+        let address = sdk::read_input_arr20();
+        let nonce = Engine::get_next_nonce(&Address(address));
+        sdk::return_output(&u256_to_arr(&nonce))
+    }
+
     #[no_mangle]
     pub extern "C" fn get_storage_at() {
         let input = sdk::read_input();
-        let args = GetStorageAtArgs::try_from_slice(&input).expect("ERR_ARG_PARSE");
+        let args = GetStorageAtArgs::try_from_slice(&input).expect("ERR_PARSE_GET_STORAGE_AT_ARGS");
         let value = Engine::get_storage(&Address(args.address), &H256(args.key));
         sdk::return_output(&value.0)
     }
 
+    /// Returns several storage slots of an address at once.
+    #[no_mangle]
+    pub extern "C" fn get_storage_slots() {
+        let input = sdk::read_input();
+        let args =
+            GetStorageSlotsArgs::try_from_slice(&input).expect("ERR_PARSE_GET_STORAGE_SLOTS_ARGS");
+        if args.keys.len() > MAX_STORAGE_SLOTS {
+            sdk::panic_utf8(b"ERR_TOO_MANY_STORAGE_SLOTS");
+        }
+        let address = Address(args.address);
+        let values: Vec<RawH256> = args
+            .keys
+            .into_iter()
+            .map(|key| Engine::get_storage(&address, &H256(key)).0)
+            .collect();
+        sdk::return_output(&values.try_to_vec().expect("ERR_SER"))
+    }
+
+    /// Returns the code of several addresses at once, bounded by the size of
+    /// the data a NEAR function call may return.
+    #[no_mangle]
+    pub extern "C" fn get_codes() {
+        let input = sdk::read_input();
+        let args = GetCodesArgs::try_from_slice(&input).expect("ERR_PARSE_GET_CODES_ARGS");
+        let addresses: Vec<Address> = args.addresses.into_iter().map(Address).collect();
+        match Engine::get_codes(&addresses, MAX_RETURN_DATA_SIZE as usize) {
+            Ok(codes) => sdk::return_output(&codes.try_to_vec().expect("ERR_SER")),
+            Err(_) => sdk::panic_utf8(b"ERR_CODES_TOO_LARGE"),
+        }
+    }
+
+    /// Returns the sum of all EVM balances.
+    /// Balances set before the sum was kept aren't part of it, so after
+    /// upgrading from such a version, `recompute_total_evm_balance` must be
+    /// called once before this is accurate.
+    #[no_mangle]
+    pub extern "C" fn get_total_evm_balance() {
+        sdk::return_output(&u256_to_arr(&Engine::get_total_balance()))
+    }
+
+    /// Returns the EVM rules transactions are executed with.
+    #[no_mangle]
+    pub extern "C" fn get_evm_config() {
+        let config = Engine::get_evm_config();
+        sdk::return_output(&config.try_to_vec().expect("ERR_SER"))
+    }
+
+    /// Returns the addresses and names of the precompiles the EVM provides.
+    #[no_mangle]
+    pub extern "C" fn get_precompiles() {
+        let precompiles = Engine::get_precompiles();
+        sdk::return_output(&precompiles.try_to_vec().expect("ERR_SER"))
+    }
+
+    /// Returns the EIP-712 struct hash the engine computes for the given typed data.
+    #[no_mangle]
+    pub extern "C" fn compute_struct_hash() {
+        let input = sdk::read_input();
+        let args = ComputeStructHashArgs::try_from_slice(&input)
+            .expect("ERR_PARSE_COMPUTE_STRUCT_HASH_ARGS");
+        match crate::meta_parsing::compute_struct_hash(&args.type_def, &args.values) {
+            Ok(hash) => sdk::return_output(&hash),
+            Err(ErrorKind::MetaTransactionTooNested) => sdk::panic_utf8(b"ERR_META_TX_TOO_NESTED"),
+            Err(_) => sdk::panic_utf8(b"ERR_INVALID_TYPED_DATA"),
+        }
+    }
+
+    /// Returns the hash of a signed Ethereum transaction, as indexed by explorers.
+    #[no_mangle]
+    pub extern "C" fn get_tx_hash() {
+        let input = sdk::read_input();
+        sdk::return_output(crate::transaction::tx_hash(&input).as_bytes())
+    }
+
+    /// Recovers the sender of a signed Ethereum transaction without executing it.
+    #[no_mangle]
+    pub extern "C" fn recover_sender() {
+        use crate::transaction::EthSignedTransaction;
+        use rlp::{Decodable, Rlp};
+
+        let input = sdk::read_input();
+        let signed_transaction = match EthSignedTransaction::decode(&Rlp::new(&input)) {
+            Ok(signed_transaction) => signed_transaction,
+            Err(_) => sdk::panic_utf8(b"ERR_INVALID_TX"),
+        };
+        match signed_transaction.sender() {
+            Some(sender) => sdk::return_output(sender.as_bytes()),
+            None => sdk::panic_utf8(b"ERR_INVALID_ECDSA_SIGNATURE"),
+        }
+    }
+
     ///
     /// BENCHMARKING METHODS
     ///
@@ -282,11 +606,30 @@ mod contract {
     #[no_mangle]
     pub extern "C" fn begin_chain() {
         let mut state = Engine::get_state();
-        require_owner_only(state);
+        require_owner_only(&state);
         let input = sdk::read_input();
-        let args = BeginChainArgs::try_from_slice(&input).expect("ERR_ARG_PARSE");
+        let args = BeginChainArgs::try_from_slice(&input).expect("ERR_PARSE_BEGIN_CHAIN_ARGS");
+        let mut total_supply = U256::zero();
+        for account in args.genesis_alloc.iter() {
+            total_supply = total_supply
+                .checked_add(U256::from(account.balance))
+                .unwrap_or_else(|| sdk::panic_utf8(b"ERR_GENESIS_SUPPLY_OVERFLOW"));
+        }
+        if let Some(expected_total_supply) = args.expected_total_supply {
+            if total_supply != U256::from(expected_total_supply) {
+                sdk::panic_utf8(b"ERR_GENESIS_SUPPLY_MISMATCH");
+            }
+        }
         state.chain_id = args.chain_id;
+        crate::log!(
+            state,
+            LogLevel::Info,
+            ["Genesis total supply: ", &total_supply.to_string()].concat()
+        );
         Engine::set_state(state);
+        for account in args.genesis_alloc {
+            Engine::set_balance(&Address(account.address), &U256::from(account.balance));
+        }
         // TODO: https://github.com/aurora-is-near/aurora-engine/issues/1
     }
 
@@ -294,32 +637,70 @@ mod contract {
     #[no_mangle]
     pub extern "C" fn begin_block() {
         let state = Engine::get_state();
-        require_owner_only(state);
+        require_owner_only(&state);
         let input = sdk::read_input();
-        let _args = BeginBlockArgs::try_from_slice(&input).expect("ERR_ARG_PARSE");
-        // TODO: https://github.com/aurora-is-near/aurora-engine/issues/2
+        let args = BeginBlockArgs::try_from_slice(&input).expect("ERR_PARSE_BEGIN_BLOCK_ARGS");
+        Engine::begin_block(args.into());
+    }
+
+    ///
+    /// TESTING METHODS
+    ///
+
+    /// Sets the balance of a single address. Only built with the
+    /// `integration-test` feature, which published builds never enable.
+    #[cfg(feature = "integration-test")]
+    #[no_mangle]
+    pub extern "C" fn set_balance_for_testing() {
+        let state = Engine::get_state();
+        require_owner_only(&state);
+        let input = sdk::read_input();
+        let args = AccountBalance::try_from_slice(&input).expect("ERR_PARSE_ACCOUNT_BALANCE");
+        Engine::set_balance(&Address(args.address), &U256::from(args.balance));
     }
 
     ///
     /// Utility methods.
     ///
 
-    fn require_owner_only(state: EngineState) {
+    fn require_owner_only(state: &EngineState) {
         if state.owner_id.as_bytes() != sdk::predecessor_account_id() {
             sdk::panic_utf8(b"ERR_NOT_ALLOWED");
         }
     }
 
-    fn predecessor_address() -> Address {
-        near_account_to_evm_address(&sdk::predecessor_account_id())
+    fn require_within_rate_limit(engine: &Engine, sender: &Address) {
+        if engine.check_rate_limit(sender).is_err() {
+            sdk::panic_utf8(b"ERR_RATE_LIMITED");
+        }
+    }
+
+    fn predecessor_address(state: &EngineState) -> Address {
+        state
+            .derivation_scheme
+            .derive_address(&sdk::predecessor_account_id())
     }
 
     fn process_exit_reason(status: ExitReason, result: &[u8]) {
         match status {
             ExitReason::Succeed(_) => sdk::return_output(result),
             ExitReason::Revert(_) => sdk::panic_hex(&result),
+            ExitReason::Error(ExitError::Other(message)) => sdk::panic_utf8(message.as_bytes()),
+            ExitReason::Error(ExitError::CreateContractLimit) => {
+                sdk::panic_utf8(b"ERR_CODE_SIZE_EXCEEDED")
+            }
+            ExitReason::Error(ExitError::CreateCollision) => {
+                sdk::panic_utf8(b"ERR_CONTRACT_ALREADY_EXISTS")
+            }
             ExitReason::Error(_error) => sdk::panic_utf8(b"error"), // TODO
             ExitReason::Fatal(_error) => sdk::panic_utf8(b"fatal error"), // TODO
         }
     }
+
+    fn process_submit_result(status: ExitReason, result: SubmitResult) {
+        match status {
+            ExitReason::Succeed(_) => sdk::return_output(&result.try_to_vec().expect("ERR_SER")),
+            status => process_exit_reason(status, &result.result),
+        }
+    }
 }
@@ -3,7 +3,7 @@ use ethabi::{encode, Token as ABIToken};
 use lunarity_lexer::{Lexer, Token};
 use rlp::{Decodable, DecoderError, Rlp};
 
-use crate::parameters::MetaCallArgs;
+use crate::parameters::{MetaCallArgs, COMPACT_SIGNATURE_V};
 use crate::precompiles::ecrecover;
 use crate::prelude::{vec, Address, Box, HashMap, String, ToOwned, ToString, Vec, H256, U256};
 use crate::types::{keccak, u256_to_arr, ErrorKind, InternalMetaCallArgs, RawU256, Result};
@@ -137,10 +137,24 @@ pub enum RlpValue {
     List(Vec<RlpValue>),
 }
 
-impl Decodable for RlpValue {
-    fn decode(rlp: &Rlp<'_>) -> core::result::Result<Self, DecoderError> {
+/// Maximum nesting depth of arrays and structs in meta call arguments, bounding
+/// the recursion of decoding and encoding them, which runs before any gas is charged.
+pub const MAX_NESTING_DEPTH: usize = 16;
+
+const TOO_NESTED: &str = "ERR_META_TX_TOO_NESTED";
+
+impl RlpValue {
+    /// Decodes a value nested `depth` levels deep in the arguments.
+    fn decode_nested(rlp: &Rlp<'_>, depth: usize) -> core::result::Result<Self, DecoderError> {
+        if depth > MAX_NESTING_DEPTH {
+            return Err(DecoderError::Custom(TOO_NESTED));
+        }
         if rlp.is_list() {
-            Ok(RlpValue::List(rlp.as_list()?))
+            Ok(RlpValue::List(
+                rlp.iter()
+                    .map(|item| Self::decode_nested(&item, depth + 1))
+                    .collect::<core::result::Result<_, _>>()?,
+            ))
         } else {
             Ok(RlpValue::Bytes(
                 rlp.decoder().decode_value(|bytes| Ok(bytes.to_vec()))?,
@@ -149,6 +163,12 @@ impl Decodable for RlpValue {
     }
 }
 
+impl Decodable for RlpValue {
+    fn decode(rlp: &Rlp<'_>) -> core::result::Result<Self, DecoderError> {
+        Self::decode_nested(rlp, 1)
+    }
+}
+
 #[derive(Debug, Eq, PartialEq)]
 /// An argument specified in a evm method definition
 pub struct Arg {
@@ -310,7 +330,10 @@ fn method_signature(method_and_type: &MethodAndTypes) -> String {
 fn rlp_decode(args: &[u8]) -> Result<Vec<RlpValue>> {
     let rlp = Rlp::new(args);
     let res: core::result::Result<Vec<RlpValue>, DecoderError> = rlp.as_list();
-    res.map_err(|_| ErrorKind::InvalidMetaTransactionFunctionArg)
+    res.map_err(|error| match error {
+        DecoderError::Custom(TOO_NESTED) => ErrorKind::MetaTransactionTooNested,
+        _ => ErrorKind::InvalidMetaTransactionFunctionArg,
+    })
 }
 
 /// eip-712 hash a single argument, whose type is ty, and value is value.
@@ -319,7 +342,11 @@ fn eip_712_hash_argument(
     ty: &ArgType,
     value: &RlpValue,
     types: &HashMap<String, Method>,
+    depth: usize,
 ) -> Result<Vec<u8>> {
+    if depth > MAX_NESTING_DEPTH {
+        return Err(ErrorKind::MetaTransactionTooNested);
+    }
     match ty {
         ArgType::String | ArgType::Bytes => {
             eip_712_rlp_value(value, |b| Ok(keccak(&b).as_bytes().to_vec()))
@@ -335,7 +362,7 @@ fn eip_712_hash_argument(
         ArgType::Array { inner, .. } => eip_712_rlp_list(value, |l| {
             let mut r = vec![];
             for element in l {
-                r.extend_from_slice(&eip_712_hash_argument(inner, element, types)?);
+                r.extend_from_slice(&eip_712_hash_argument(inner, element, types, depth + 1)?);
             }
             Ok(keccak(&r).as_bytes().to_vec())
         }),
@@ -351,6 +378,7 @@ fn eip_712_hash_argument(
                     &struct_type.args[i].t,
                     element,
                     types,
+                    depth + 1,
                 )?);
             }
             Ok(keccak(&r).as_bytes().to_vec())
@@ -385,12 +413,25 @@ where
 fn eth_abi_encode_args(args_decoded: &[RlpValue], methods: &MethodAndTypes) -> Result<Vec<u8>> {
     let mut tokens = vec![];
     for (i, arg) in args_decoded.iter().enumerate() {
-        tokens.push(arg_to_abi_token(&methods.method.args[i].t, arg, methods)?);
+        tokens.push(arg_to_abi_token(
+            &methods.method.args[i].t,
+            arg,
+            methods,
+            1,
+        )?);
     }
     Ok(encode(&tokens))
 }
 
-fn arg_to_abi_token(ty: &ArgType, arg: &RlpValue, methods: &MethodAndTypes) -> Result<ABIToken> {
+fn arg_to_abi_token(
+    ty: &ArgType,
+    arg: &RlpValue,
+    methods: &MethodAndTypes,
+    depth: usize,
+) -> Result<ABIToken> {
+    if depth > MAX_NESTING_DEPTH {
+        return Err(ErrorKind::MetaTransactionTooNested);
+    }
     match ty {
         ArgType::String | ArgType::Bytes => {
             value_to_abi_token(arg, |b| Ok(ABIToken::Bytes(b.clone())))
@@ -408,7 +449,7 @@ fn arg_to_abi_token(ty: &ArgType, arg: &RlpValue, methods: &MethodAndTypes) -> R
         } => list_to_abi_token(arg, |l| {
             let mut tokens = vec![];
             for arg in l {
-                tokens.push(arg_to_abi_token(inner, arg, methods)?);
+                tokens.push(arg_to_abi_token(inner, arg, methods, depth + 1)?);
             }
             Ok(ABIToken::Array(tokens))
         }),
@@ -418,7 +459,7 @@ fn arg_to_abi_token(ty: &ArgType, arg: &RlpValue, methods: &MethodAndTypes) -> R
         } => list_to_abi_token(arg, |l| {
             let mut tokens = vec![];
             for arg in l {
-                tokens.push(arg_to_abi_token(inner, arg, methods)?);
+                tokens.push(arg_to_abi_token(inner, arg, methods, depth + 1)?);
             }
             Ok(ABIToken::FixedArray(tokens))
         }),
@@ -429,7 +470,12 @@ fn arg_to_abi_token(ty: &ArgType, arg: &RlpValue, methods: &MethodAndTypes) -> R
                 .ok_or(ErrorKind::InvalidMetaTransactionFunctionArg)?;
             let mut tokens = vec![];
             for (i, element) in l.iter().enumerate() {
-                tokens.push(arg_to_abi_token(&struct_type.args[i].t, element, methods)?);
+                tokens.push(arg_to_abi_token(
+                    &struct_type.args[i].t,
+                    element,
+                    methods,
+                    depth + 1,
+                )?);
             }
             Ok(ABIToken::Tuple(tokens))
         }),
@@ -456,7 +502,38 @@ where
     }
 }
 
-/// eip-712 hash struct of entire meta txn and abi-encode function args to evm input
+/// eip-712 hash struct of the first struct of `type_def`, whose values are `values`.
+/// The typeHash is taken over the whole of `type_def` as given, while structs nested in the
+/// values are hashed as in a meta call.
+fn eip_712_hash_struct(
+    type_def: &str,
+    methods: &MethodAndTypes,
+    values: &[RlpValue],
+) -> Result<RawU256> {
+    let mut bytes = keccak(type_def.as_bytes()).as_bytes().to_vec();
+    for (arg, value) in methods.method.args.iter().zip(values) {
+        bytes.extend_from_slice(&eip_712_hash_argument(&arg.t, value, &methods.types, 1)?);
+    }
+    Ok(keccak(&bytes).into())
+}
+
+/// Compute the eip-712 struct hash the engine would compute for typed data, given the struct
+/// definition followed by the definitions of the structs it references (e.g.
+/// "Mail(Person from,string contents)Person(string name,address wallet)", with no space after
+/// `,`) and its rlp-encoded list of values. This is the hash a meta call computes for its
+/// `Arguments`, so wallets can compare their hashing against the engine's.
+pub fn compute_struct_hash(type_def: &str, values: &[u8]) -> Result<RawU256> {
+    let methods = MethodAndTypes::parse(type_def)?;
+    let values_decoded = rlp_decode(values)?;
+    if values_decoded.len() != methods.method.args.len() {
+        return Err(ErrorKind::InvalidMetaTransactionFunctionArg);
+    }
+    eip_712_hash_struct(type_def, &methods, &values_decoded)
+}
+
+/// eip-712 hash struct of entire meta txn and abi-encode function args to evm input.
+/// The hash covers the engine's account id, the nonce, the fee, the contract address, the value
+/// and the full call (method and arguments), so a signature authorizes only the exact call signed.
 pub fn prepare_meta_call_args(
     domain_separator: &RawU256,
     account_id: &[u8],
@@ -486,16 +563,8 @@ pub fn prepare_meta_call_args(
     let method_sig = method_signature(&methods);
     bytes.extend_from_slice(&keccak(method_sig.as_bytes()).as_bytes());
 
-    let mut arg_bytes = Vec::new();
-    arg_bytes.extend_from_slice(&keccak(arguments.as_bytes()).as_bytes());
     let args_decoded: Vec<RlpValue> = rlp_decode(&input.input)?;
-    for (i, arg) in args_decoded.iter().enumerate() {
-        arg_bytes.extend_from_slice(&eip_712_hash_argument(
-            &methods.method.args[i].t,
-            arg,
-            &methods.types,
-        )?);
-    }
+    let arg_bytes_hash = eip_712_hash_struct(&arguments, &methods, &args_decoded)?;
 
     // ETH-ABI require function selector to use method_sig, instead of method_name,
     // See https://docs.soliditylang.org/en/v0.7.5/abi-spec.html#function-selector
@@ -506,7 +575,6 @@ pub fn prepare_meta_call_args(
     let args_eth_abi = eth_abi_encode_args(&args_decoded, &methods)?;
     let input = [method_selector.to_vec(), args_eth_abi.to_vec()].concat();
 
-    let arg_bytes_hash: RawU256 = keccak(&arg_bytes).into();
     bytes.extend_from_slice(&arg_bytes_hash);
 
     let message: RawU256 = keccak(&bytes).into();
@@ -545,7 +613,12 @@ pub fn parse_meta_call(
     let mut signature: [u8; 65] = [0; 65];
     signature[64] = meta_tx.v;
     signature[..64].copy_from_slice(&meta_tx.signature);
-    match ecrecover(H256::from_slice(&msg), &signature) {
+    let signature = if meta_tx.v == COMPACT_SIGNATURE_V {
+        &signature[..64]
+    } else {
+        &signature[..]
+    };
+    match ecrecover(H256::from_slice(&msg), signature) {
         Ok(sender) => {
             result.sender = sender;
             result.input = input;
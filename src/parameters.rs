@@ -1,7 +1,61 @@
 use borsh::{BorshDeserialize, BorshSerialize};
+use evm::backend::Log;
+use evm::{Config, ExitError, ExitReason};
 
-use crate::prelude::{String, Vec};
-use crate::types::{AccountId, RawAddress, RawH256, RawU256};
+use crate::prelude::{Address, String, ToString, Vec};
+use crate::types::{
+    near_account_to_evm_address, AccountId, ErrorKind, RawAddress, RawH256, RawU256,
+};
+
+/// How NEAR account ids map to the EVM addresses of their callers.
+///
+/// The scheme determines which EVM address holds the balance, nonce and
+/// contracts of a NEAR account, so it is fixed when the engine is first
+/// initialized: changing it would orphan everything held under the old
+/// addresses. Bridges and tools deriving addresses off-chain must use the
+/// same scheme as the engine.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DerivationScheme {
+    /// The last 20 bytes of the keccak-256 hash of the account id.
+    Keccak,
+}
+
+impl Default for DerivationScheme {
+    fn default() -> Self {
+        DerivationScheme::Keccak
+    }
+}
+
+impl DerivationScheme {
+    pub fn derive_address(self, account_id: &[u8]) -> Address {
+        match self {
+            DerivationScheme::Keccak => near_account_to_evm_address(account_id),
+        }
+    }
+}
+
+/// Verbosity of the engine's diagnostic messages, from least to most verbose.
+/// EVM logs are the output of transactions and are always emitted.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub enum LogLevel {
+    Off,
+    Error,
+    Info,
+    Debug,
+}
+
+impl Default for LogLevel {
+    fn default() -> Self {
+        LogLevel::Info
+    }
+}
+
+impl LogLevel {
+    /// Whether messages of the given level are logged at this verbosity.
+    pub fn enables(self, level: LogLevel) -> bool {
+        level != LogLevel::Off && level <= self
+    }
+}
 
 /// Borsh-encoded parameters for the `new` function.
 #[derive(BorshSerialize, BorshDeserialize)]
@@ -16,12 +70,128 @@ pub struct NewCallArgs {
     pub bridge_prover_id: AccountId,
     /// How many blocks after staging upgrade can deploy it.
     pub upgrade_delay_blocks: u64,
+    /// Maximum size in bytes of the EVM input accepted by `view`.
+    pub max_view_input_size: u64,
+    /// Address returned by the COINBASE opcode as the block's beneficiary.
+    pub fee_recipient: RawAddress,
+    /// Maximum number of logs a transaction may emit; at least 1.
+    pub max_logs_per_transaction: u64,
+    /// Whether `raw_call` rejects transactions without an EIP-155 chain id.
+    pub require_chain_id: bool,
+    /// How NEAR account ids map to EVM addresses; can't change once set.
+    pub derivation_scheme: DerivationScheme,
+    /// Verbosity of the engine's diagnostic messages.
+    pub log_level: LogLevel,
+    /// Lowest gas price `raw_call` accepts transactions with.
+    pub min_gas_price: RawU256,
+    /// Maximum size in bytes of the EVM return data of a call or view; at
+    /// least 1 and at most `MAX_RETURN_DATA_SIZE`.
+    pub max_return_size: u64,
+    /// Whether calls and transfers to the zero address are rejected rather
+    /// than executed as an ordinary call to it.
+    pub reject_send_to_zero: bool,
+    /// Limit on the number of transactions of each sender, if any.
+    pub rate_limit: Option<RateLimit>,
+    /// Whether `call` fails for a target without code rather than succeeding
+    /// without doing anything, to surface misconfigured target addresses.
+    pub reject_call_without_code: bool,
+}
+
+/// Default `max_view_input_size`.
+pub const DEFAULT_MAX_VIEW_INPUT_SIZE: u64 = 64 * 1024;
+
+/// Default `max_logs_per_transaction`, high enough for any ordinary contract.
+pub const DEFAULT_MAX_LOGS_PER_TRANSACTION: u64 = 1024;
+
+/// Maximum size in bytes of the data a NEAR function call may return, which is
+/// also the default `max_return_size`.
+pub const MAX_RETURN_DATA_SIZE: u64 = 4 * 1024 * 1024;
+
+impl Default for NewCallArgs {
+    fn default() -> Self {
+        NewCallArgs {
+            chain_id: [0u8; 32],
+            owner_id: AccountId::default(),
+            bridge_prover_id: AccountId::default(),
+            upgrade_delay_blocks: 0,
+            max_view_input_size: DEFAULT_MAX_VIEW_INPUT_SIZE,
+            fee_recipient: [0u8; 20],
+            max_logs_per_transaction: DEFAULT_MAX_LOGS_PER_TRANSACTION,
+            require_chain_id: false,
+            derivation_scheme: DerivationScheme::default(),
+            log_level: LogLevel::default(),
+            min_gas_price: [0u8; 32],
+            max_return_size: MAX_RETURN_DATA_SIZE,
+            reject_send_to_zero: false,
+            rate_limit: None,
+            reject_call_without_code: false,
+        }
+    }
+}
+
+impl NewCallArgs {
+    /// Deserializes the arguments, also accepting those of the engine's first
+    /// version, which leave the settings added since at their defaults.
+    pub fn try_from_slice_compatible(bytes: &[u8]) -> core::result::Result<Self, ErrorKind> {
+        Self::try_from_slice(bytes)
+            .or_else(|_| LegacyNewCallArgs::try_from_slice(bytes).map(Self::from))
+            .map_err(|_| ErrorKind::ArgumentParseError)
+    }
+
+    /// Checks that the limits leave the engine usable, as a zero limit would
+    /// fail every transaction it applies to, and that they are within what a
+    /// NEAR function call can return.
+    pub fn validate(&self) -> core::result::Result<(), ErrorKind> {
+        if self.max_logs_per_transaction == 0 {
+            return Err(ErrorKind::InvalidMaxLogsPerTransaction);
+        }
+        if self.max_return_size == 0 || self.max_return_size > MAX_RETURN_DATA_SIZE {
+            return Err(ErrorKind::InvalidMaxReturnSize);
+        }
+        Ok(())
+    }
+}
+
+/// Borsh-encoded parameters for the `new` function of the engine's first
+/// version, which stored its state in this layout too.
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct LegacyNewCallArgs {
+    pub chain_id: RawU256,
+    pub owner_id: AccountId,
+    pub bridge_prover_id: AccountId,
+    pub upgrade_delay_blocks: u64,
 }
 
+impl From<LegacyNewCallArgs> for NewCallArgs {
+    fn from(args: LegacyNewCallArgs) -> Self {
+        NewCallArgs {
+            chain_id: args.chain_id,
+            owner_id: args.owner_id,
+            bridge_prover_id: args.bridge_prover_id,
+            upgrade_delay_blocks: args.upgrade_delay_blocks,
+            ..NewCallArgs::default()
+        }
+    }
+}
+
+/// Limits each sender to `max_transactions` transactions per window of
+/// `window_blocks` blocks.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, Eq, PartialEq)]
+pub struct RateLimit {
+    pub max_transactions: u64,
+    pub window_blocks: u64,
+}
+
+/// The `v` of a `MetaCallArgs` whose signature is compact (EIP-2098). It is
+/// no valid recovery id, so it can't be mistaken for a full signature's `v`.
+pub const COMPACT_SIGNATURE_V: u8 = 0xff;
+
 /// Borsh-encoded parameters for the `meta_call` function.
 #[derive(Debug, BorshSerialize, BorshDeserialize)]
 pub struct MetaCallArgs {
     pub signature: [u8; 64],
+    /// The recovery id, optionally plus 27, or `COMPACT_SIGNATURE_V` for a
+    /// compact signature keeping the y parity in the top bit of `s` (EIP-2098).
     pub v: u8,
     pub nonce: RawU256,
     pub fee_amount: RawU256,
@@ -48,6 +218,158 @@ pub struct ViewCallArgs {
     pub input: Vec<u8>,
 }
 
+/// Size of the fixed-width prefix of `ViewCallArgs`: sender, address, amount
+/// and the length of the input.
+const VIEW_CALL_ARGS_HEADER_SIZE: u64 = 20 + 20 + 32 + 4;
+
+impl ViewCallArgs {
+    /// Deserializes the arguments, rejecting inputs larger than `max_input_size` bytes
+    /// before any decoding takes place.
+    pub fn try_from_slice_bounded(
+        bytes: &[u8],
+        max_input_size: u64,
+    ) -> core::result::Result<Self, ErrorKind> {
+        if bytes.len() as u64 > VIEW_CALL_ARGS_HEADER_SIZE.saturating_add(max_input_size) {
+            return Err(ErrorKind::ViewInputTooLarge);
+        }
+        Self::try_from_slice(bytes).map_err(|_| ErrorKind::ArgumentParseError)
+    }
+}
+
+/// Borsh-encoded log for use in a `SubmitResult`.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Eq, PartialEq)]
+pub struct ResultLog {
+    pub address: RawAddress,
+    pub topics: Vec<RawU256>,
+    pub data: Vec<u8>,
+}
+
+impl From<Log> for ResultLog {
+    fn from(log: Log) -> Self {
+        ResultLog {
+            address: log.address.0,
+            topics: log.topics.into_iter().map(|topic| topic.0).collect(),
+            data: log.data,
+        }
+    }
+}
+
+/// Borsh-encoded result of the `deploy_code`, `call`, `raw_call` and `meta_call` functions.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Eq, PartialEq)]
+pub struct SubmitResult {
+    /// The amount of EVM gas used by the transaction.
+    pub gas_used: u64,
+    /// The output of the transaction, or the new contract's address for deployments.
+    pub result: Vec<u8>,
+    /// The logs emitted by the transaction.
+    pub logs: Vec<ResultLog>,
+    /// The NEAR block index the transaction was executed at.
+    pub block_height: u64,
+    /// The number of storage slots the transaction wrote a non-zero value to.
+    pub storage_writes: u64,
+    /// The number of storage slots the transaction wrote zero to, clearing them.
+    /// Slots of self-destructed accounts or of reset storage aren't included.
+    pub storage_zero_writes: u64,
+}
+
+/// Borsh-encoded entry of the result of the `get_precompiles` function.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Eq, PartialEq)]
+pub struct PrecompileInfo {
+    pub address: RawAddress,
+    /// Short identifier of the precompile, such as `ecrecover`.
+    pub name: String,
+}
+
+/// Borsh-encoded result of the `get_evm_config` function, describing the EVM
+/// rules the engine executes transactions with.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Eq, PartialEq)]
+pub struct EvmConfigResult {
+    /// Name of the hardfork whose rules are active, e.g. "istanbul".
+    pub hardfork: String,
+    pub stack_limit: u64,
+    pub memory_limit: u64,
+    pub call_stack_limit: u64,
+    /// Maximum size of deployed code, if limited (EIP-170).
+    pub create_contract_limit: Option<u64>,
+    pub call_stipend: u64,
+    pub gas_transaction_call: u64,
+    pub gas_transaction_create: u64,
+    pub gas_transaction_zero_data: u64,
+    pub gas_transaction_non_zero_data: u64,
+    /// Whether net gas metering for SSTORE is on (EIP-2200).
+    pub sstore_gas_metering: bool,
+    pub empty_considered_exists: bool,
+    pub has_delegate_call: bool,
+    pub has_create2: bool,
+    pub has_revert: bool,
+    pub has_return_data: bool,
+    pub has_bitwise_shifting: bool,
+    pub has_chain_id: bool,
+    pub has_self_balance: bool,
+    pub has_ext_code_hash: bool,
+    /// Whether access lists and cold/warm access costs are on (EIP-2929/EIP-2930).
+    pub has_access_lists: bool,
+}
+
+impl EvmConfigResult {
+    pub fn new(hardfork: &str, config: &Config) -> Self {
+        EvmConfigResult {
+            hardfork: hardfork.to_string(),
+            stack_limit: config.stack_limit as u64,
+            memory_limit: config.memory_limit as u64,
+            call_stack_limit: config.call_stack_limit as u64,
+            create_contract_limit: config.create_contract_limit.map(|limit| limit as u64),
+            call_stipend: config.call_stipend,
+            gas_transaction_call: config.gas_transaction_call,
+            gas_transaction_create: config.gas_transaction_create,
+            gas_transaction_zero_data: config.gas_transaction_zero_data,
+            gas_transaction_non_zero_data: config.gas_transaction_non_zero_data,
+            sstore_gas_metering: config.sstore_gas_metering,
+            empty_considered_exists: config.empty_considered_exists,
+            has_delegate_call: config.has_delegate_call,
+            has_create2: config.has_create2,
+            has_revert: config.has_revert,
+            has_return_data: config.has_return_data,
+            has_bitwise_shifting: config.has_bitwise_shifting,
+            has_chain_id: config.has_chain_id,
+            has_self_balance: config.has_self_balance,
+            has_ext_code_hash: config.has_ext_code_hash,
+            // Not supported by this version of SputnikVM.
+            has_access_lists: false,
+        }
+    }
+}
+
+/// Outcome of a `view` call, telling apart the ways it can fail.
+#[derive(Debug, PartialEq)]
+pub enum TransactionStatus {
+    /// The call succeeded with the given output.
+    Succeed(Vec<u8>),
+    /// The call reverted with the given data.
+    Revert(Vec<u8>),
+    OutOfGas,
+    /// The call failed for another reason.
+    Failed(ExitReason),
+}
+
+impl TransactionStatus {
+    pub fn new(reason: ExitReason, output: Vec<u8>) -> Self {
+        match reason {
+            ExitReason::Succeed(_) => TransactionStatus::Succeed(output),
+            ExitReason::Revert(_) => TransactionStatus::Revert(output),
+            ExitReason::Error(ExitError::OutOfGas) => TransactionStatus::OutOfGas,
+            reason => TransactionStatus::Failed(reason),
+        }
+    }
+}
+
+/// Borsh-encoded parameters for the `get_balance_at` function.
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct GetBalanceAtArgs {
+    pub address: RawAddress,
+    pub block_height: u64,
+}
+
 /// Borsh-encoded parameters for the `get_storage_at` function.
 #[derive(BorshSerialize, BorshDeserialize)]
 pub struct GetStorageAtArgs {
@@ -55,11 +377,69 @@ pub struct GetStorageAtArgs {
     pub key: RawH256,
 }
 
+/// Borsh-encoded parameters for the `get_storage_slots` function.
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct GetStorageSlotsArgs {
+    pub address: RawAddress,
+    pub keys: Vec<RawH256>,
+}
+
+/// Borsh-encoded parameters for the `get_codes` function.
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct GetCodesArgs {
+    pub addresses: Vec<RawAddress>,
+}
+
+/// Borsh-encoded parameters for the `recompute_total_evm_balance` function.
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct RecomputeTotalBalanceArgs {
+    /// Every address holding a balance.
+    pub addresses: Vec<RawAddress>,
+}
+
+/// Borsh-encoded parameters for the `burn` function.
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct BurnArgs {
+    pub address: RawAddress,
+    pub amount: RawU256,
+}
+
+/// Borsh-encoded parameters for the `set_chain_id` function.
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct SetChainIdArgs {
+    pub chain_id: RawU256,
+    /// Must be set, acknowledging that pending signed transactions and
+    /// meta-call signatures for the old chain id become invalid.
+    pub confirm: bool,
+}
+
+/// Borsh-encoded parameters for the `compute_struct_hash` function.
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct ComputeStructHashArgs {
+    /// Struct definition followed by the definitions of the structs it references.
+    pub type_def: String,
+    /// RLP-encoded list of the struct's values.
+    pub values: Vec<u8>,
+}
+
 /// Borsh-encoded parameters for the `begin_chain` function.
 #[cfg(feature = "evm_bully")]
 #[derive(BorshSerialize, BorshDeserialize)]
 pub struct BeginChainArgs {
     pub chain_id: RawU256,
+    /// The balances to allocate at genesis.
+    pub genesis_alloc: Vec<AccountBalance>,
+    /// The total supply the genesis allocation must add up to, if any.
+    pub expected_total_supply: Option<RawU256>,
+}
+
+/// Borsh-encoded account balance used in the `begin_chain` genesis allocation
+/// and by `set_balance_for_testing`.
+#[cfg(any(feature = "evm_bully", feature = "integration-test"))]
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct AccountBalance {
+    pub address: RawAddress,
+    pub balance: RawU256,
 }
 
 /// Borsh-encoded parameters for the `begin_block` function.
@@ -102,4 +482,134 @@ mod tests {
         let res = ViewCallArgs::try_from_slice(&bytes).unwrap();
         assert_eq!(x, res);
     }
+
+    #[test]
+    fn test_roundtrip_submit_result() {
+        let x = SubmitResult {
+            gas_used: 21_000,
+            result: vec![1, 2, 3],
+            logs: vec![ResultLog {
+                address: [1; 20],
+                topics: vec![[2; 32]],
+                data: vec![3],
+            }],
+            block_height: 42,
+            storage_writes: 2,
+            storage_zero_writes: 1,
+        };
+        let bytes = x.try_to_vec().unwrap();
+        let res = SubmitResult::try_from_slice(&bytes).unwrap();
+        assert_eq!(x, res);
+    }
+
+    #[test]
+    fn test_view_call_bounded() {
+        let x = ViewCallArgs {
+            sender: [1; 20],
+            address: [2; 20],
+            amount: [3; 32],
+            input: vec![4; 16],
+        };
+        let bytes = x.try_to_vec().unwrap();
+        let res = ViewCallArgs::try_from_slice_bounded(&bytes, 16)
+            .ok()
+            .unwrap();
+        assert_eq!(x, res);
+        assert!(matches!(
+            ViewCallArgs::try_from_slice_bounded(&bytes, 15),
+            Err(ErrorKind::ViewInputTooLarge)
+        ));
+        assert!(matches!(
+            ViewCallArgs::try_from_slice_bounded(&bytes[..bytes.len() - 1], 16),
+            Err(ErrorKind::ArgumentParseError)
+        ));
+    }
+
+    #[test]
+    fn test_new_call_args_compatible() {
+        let legacy = LegacyNewCallArgs {
+            chain_id: [1; 32],
+            owner_id: "owner.near".into(),
+            bridge_prover_id: "prover.near".into(),
+            upgrade_delay_blocks: 3,
+        };
+        let bytes = legacy.try_to_vec().unwrap();
+        let args = NewCallArgs::try_from_slice_compatible(&bytes).ok().unwrap();
+        assert_eq!(args.chain_id, [1; 32]);
+        assert_eq!(args.owner_id, "owner.near");
+        assert_eq!(args.upgrade_delay_blocks, 3);
+        assert_eq!(
+            args.max_logs_per_transaction,
+            DEFAULT_MAX_LOGS_PER_TRANSACTION
+        );
+        assert_eq!(args.max_return_size, MAX_RETURN_DATA_SIZE);
+
+        let bytes = NewCallArgs {
+            max_return_size: 1024,
+            ..NewCallArgs::from(legacy)
+        }
+        .try_to_vec()
+        .unwrap();
+        let args = NewCallArgs::try_from_slice_compatible(&bytes).ok().unwrap();
+        assert_eq!(args.bridge_prover_id, "prover.near");
+        assert_eq!(args.max_return_size, 1024);
+        assert!(matches!(
+            NewCallArgs::try_from_slice_compatible(&bytes[..bytes.len() - 1]),
+            Err(ErrorKind::ArgumentParseError)
+        ));
+    }
+
+    #[test]
+    fn test_new_call_args_validate() {
+        assert!(NewCallArgs::default().validate().is_ok());
+        let args = NewCallArgs {
+            max_logs_per_transaction: 0,
+            ..NewCallArgs::default()
+        };
+        assert!(matches!(
+            args.validate(),
+            Err(ErrorKind::InvalidMaxLogsPerTransaction)
+        ));
+        let args = NewCallArgs {
+            max_logs_per_transaction: 1,
+            ..NewCallArgs::default()
+        };
+        assert!(args.validate().is_ok());
+        for max_return_size in [0, MAX_RETURN_DATA_SIZE + 1].iter() {
+            let args = NewCallArgs {
+                max_return_size: *max_return_size,
+                ..NewCallArgs::default()
+            };
+            assert!(matches!(
+                args.validate(),
+                Err(ErrorKind::InvalidMaxReturnSize)
+            ));
+        }
+        let args = NewCallArgs {
+            max_return_size: 1,
+            ..NewCallArgs::default()
+        };
+        assert!(args.validate().is_ok());
+    }
+
+    #[test]
+    fn test_derivation_scheme() {
+        let scheme = DerivationScheme::default();
+        assert_eq!(scheme, DerivationScheme::Keccak);
+        for account_id in [&b"aurora"[..], b"alice.near", b""].iter() {
+            assert_eq!(
+                scheme.derive_address(account_id),
+                near_account_to_evm_address(account_id)
+            );
+        }
+    }
+
+    #[test]
+    fn test_log_level_enables() {
+        assert!(LogLevel::Info.enables(LogLevel::Error));
+        assert!(LogLevel::Info.enables(LogLevel::Info));
+        assert!(!LogLevel::Info.enables(LogLevel::Debug));
+        assert!(!LogLevel::Off.enables(LogLevel::Error));
+        assert!(!LogLevel::Debug.enables(LogLevel::Off));
+    }
 }
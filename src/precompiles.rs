@@ -1,8 +1,116 @@
-use crate::prelude::{Address, Borrowed, Vec, H160, H256, U256};
+use crate::prelude::{vec, Address, Borrowed, ToString, Vec, H160, H256, U256};
+use crate::types::keccak;
 use evm::{Context, ExitError, ExitSucceed};
 
 type PrecompileResult = Result<(ExitSucceed, Vec<u8>, u64), ExitError>;
 
+/// Gas cost of a precompile: a base cost plus a cost per 32-byte word of input.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct LinearCost {
+    pub base: u64,
+    pub per_word: u64,
+}
+
+impl LinearCost {
+    pub fn cost(&self, input_len: usize) -> u64 {
+        let words = (input_len as u64).saturating_add(31) / 32;
+        self.base
+            .saturating_add(self.per_word.saturating_mul(words))
+    }
+}
+
+/// Gas costs of the precompiles, kept in a single table so that a hardfork
+/// repricing is a data change.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct PrecompileCosts {
+    pub ecrecover: LinearCost,
+    pub sha256: LinearCost,
+    pub ripemd160: LinearCost,
+    pub identity: LinearCost,
+    /// Engine-specific: ecrecover plus keccak-256 of the prefixed message.
+    pub personal_ecrecover: LinearCost,
+    /// Engine-specific: priced as a LOG0 of the message.
+    pub near_log: LinearCost,
+    /// Engine-specific: a fixed cost, as it reads no input.
+    pub attached_deposit: LinearCost,
+}
+
+impl PrecompileCosts {
+    /// Mainnet costs, unchanged from Frontier up to Istanbul.
+    /// See: https://ethereum.github.io/yellowpaper/paper.pdf (appendix E)
+    pub const fn istanbul() -> Self {
+        Self {
+            ecrecover: LinearCost {
+                base: 3000,
+                per_word: 0,
+            },
+            sha256: LinearCost {
+                base: 60,
+                per_word: 12,
+            },
+            ripemd160: LinearCost {
+                base: 600,
+                per_word: 120,
+            },
+            identity: LinearCost {
+                base: 15,
+                per_word: 3,
+            },
+            personal_ecrecover: LinearCost {
+                base: 3000 + 30,
+                per_word: 6,
+            },
+            near_log: LinearCost {
+                base: 375,
+                per_word: 8 * 32,
+            },
+            attached_deposit: LinearCost {
+                base: 15,
+                per_word: 0,
+            },
+        }
+    }
+}
+
+impl Default for PrecompileCosts {
+    fn default() -> Self {
+        Self::istanbul()
+    }
+}
+
+/// Address of the engine-specific precompile recovering the signer of an
+/// EIP-191 `personal_sign` message.
+pub const PERSONAL_ECRECOVER_ADDRESS: u64 = 0x100;
+
+/// Address of the engine-specific precompile writing its UTF-8 input to the
+/// NEAR transaction log.
+#[cfg(any(feature = "contract", feature = "standalone"))]
+pub const NEAR_LOG_ADDRESS: u64 = 0x101;
+
+/// Address of the engine-specific precompile returning the NEAR deposit
+/// attached to the current NEAR transaction.
+#[cfg(any(feature = "contract", feature = "standalone"))]
+pub const ATTACHED_DEPOSIT_ADDRESS: u64 = 0x102;
+
+/// The precompiles `istanbul_precompiles` implements, by address.
+#[cfg(any(feature = "contract", feature = "standalone"))]
+pub const ISTANBUL_PRECOMPILES: &[(u64, &str)] = &[
+    (1, "ecrecover"),
+    (2, "sha256"),
+    (3, "ripemd160"),
+    (4, "identity"),
+    (PERSONAL_ECRECOVER_ADDRESS, "personal_ecrecover"),
+    (NEAR_LOG_ADDRESS, "near_log"),
+    (ATTACHED_DEPOSIT_ADDRESS, "attached_deposit"),
+];
+
+/// Longest message the NEAR log precompile accepts, within NEAR's log limits.
+#[cfg(any(feature = "contract", feature = "standalone"))]
+pub const MAX_NEAR_LOG_LENGTH: usize = 16 * 1024;
+
+/// The cost table charged by `istanbul_precompiles`.
+pub(crate) const ISTANBUL_COSTS: PrecompileCosts = PrecompileCosts::istanbul();
+
 #[allow(dead_code)]
 pub fn no_precompiles(
     _address: Address,
@@ -17,51 +125,137 @@ pub fn no_precompiles(
 pub fn istanbul_precompiles(
     address: Address,
     input: &[u8],
-    _target_gas: Option<u64>,
+    target_gas: Option<u64>,
     _context: &Context,
 ) -> Option<PrecompileResult> {
+    let costs = &ISTANBUL_COSTS;
     match address.to_low_u64_be() {
-        1 => Some(Ok((
-            ExitSucceed::Returned,
-            ecrecover_raw(input).as_bytes().to_vec(),
-            0,
-        ))),
-        2 => Some(Ok((
-            ExitSucceed::Returned,
-            sha256(input).as_bytes().to_vec(),
-            0,
-        ))),
-        3 => Some(Ok((
-            ExitSucceed::Returned,
-            ripemd160(input).as_bytes().to_vec(),
-            0,
-        ))),
-        4 => Some(Ok((ExitSucceed::Returned, identity(input).to_vec(), 0))),
-        5 => todo!(), // TODO: implement modexp()
-        6 => todo!(), // TODO: implement alt_bn128_add()
-        7 => todo!(), // TODO: implement alt_bn128_mul()
-        8 => todo!(), // TODO: implement alt_bn128_pair()
-        9 => todo!(), // TODO: implement blake2f()
+        1 => Some(charge(
+            costs.ecrecover.cost(input.len()),
+            target_gas,
+            || {
+                let mut output = vec![0u8; 12];
+                output.extend_from_slice(ecrecover_raw(input).as_bytes());
+                output
+            },
+        )),
+        2 => Some(charge(costs.sha256.cost(input.len()), target_gas, || {
+            sha256(input).as_bytes().to_vec()
+        })),
+        3 => Some(charge(
+            costs.ripemd160.cost(input.len()),
+            target_gas,
+            || ripemd160(input).as_bytes().to_vec(),
+        )),
+        4 => Some(charge(costs.identity.cost(input.len()), target_gas, || {
+            identity(input).to_vec()
+        })),
+        5 => Some(not_implemented()), // TODO: implement modexp()
+        6 => Some(not_implemented()), // TODO: implement alt_bn128_add()
+        7 => Some(not_implemented()), // TODO: implement alt_bn128_mul()
+        8 => Some(not_implemented()), // TODO: implement alt_bn128_pair()
+        9 => Some(not_implemented()), // TODO: implement blake2f()
+        PERSONAL_ECRECOVER_ADDRESS => Some(charge(
+            costs.personal_ecrecover.cost(input.len()),
+            target_gas,
+            || personal_ecrecover(input),
+        )),
+        #[cfg(any(feature = "contract", feature = "standalone"))]
+        NEAR_LOG_ADDRESS => Some(near_log(
+            input,
+            costs.near_log.cost(input.len()),
+            target_gas,
+        )),
+        #[cfg(any(feature = "contract", feature = "standalone"))]
+        ATTACHED_DEPOSIT_ADDRESS => Some(charge(
+            costs.attached_deposit.cost(input.len()),
+            target_gas,
+            || crate::sdk::attached_deposit().to_be_bytes().to_vec(),
+        )),
         // Not supported.
         _ => None,
     }
 }
 
+/// Runs a precompile costing `cost` gas, provided `target_gas` can cover it.
+/// Both profiles build with `panic = "abort"`, so a panicking precompile would
+/// abort the whole contract call: precompiles must bounds-check their input.
+fn charge<F: FnOnce() -> Vec<u8>>(cost: u64, target_gas: Option<u64>, run: F) -> PrecompileResult {
+    match target_gas {
+        Some(target_gas) if cost > target_gas => Err(ExitError::OutOfGas),
+        _ => Ok((ExitSucceed::Returned, run(), cost)),
+    }
+}
+
+fn not_implemented() -> PrecompileResult {
+    Err(ExitError::Other(Borrowed("ERR_PRECOMPILE_NOT_IMPLEMENTED")))
+}
+
+/// Recovers the signer as the ecrecover precompile does, returning the zero
+/// address rather than failing if the input isn't a valid signature.
 #[allow(dead_code)]
 fn ecrecover_raw(input: &[u8]) -> Address {
-    assert_eq!(input.len(), 128); // input is (hash, v, r, s), each typed as a uint256
+    // input is (hash, v, r, s), each typed as a uint256, implicitly right-padded with zeros
+    let mut padded_input = [0u8; 128];
+    let len = core::cmp::min(input.len(), padded_input.len());
+    padded_input[..len].copy_from_slice(&input[..len]);
+    let input = &padded_input;
 
     let mut hash = [0; 32];
     hash.copy_from_slice(&input[0..32]);
 
+    // v is a uint256 which must be exactly 27 or 28; unlike for transactions,
+    // an s in the upper half of the curve order is accepted.
+    if input[32..63].iter().any(|byte| *byte != 0) || !matches!(input[63], 27 | 28) {
+        return Address::zero();
+    }
+
     let mut signature = [0; 65]; // signature is (r, s, v), typed (uint256, uint256, uint8)
-    signature[0..32].copy_from_slice(&input[64..]); // r
-    signature[32..64].copy_from_slice(&input[96..]); // s
+    signature[0..32].copy_from_slice(&input[64..96]); // r
+    signature[32..64].copy_from_slice(&input[96..128]); // s
     signature[64] = input[63]; // v
 
     ecrecover(H256::from_slice(&hash), &signature).unwrap_or_else(|_| Address::zero())
 }
 
+/// Recovers the signer of an EIP-191 `personal_sign` message, given as the
+/// 65-byte signature (r, s, v) followed by the message itself. Returns the
+/// signer padded to 32 bytes, or nothing if the signature is invalid.
+/// See: https://eips.ethereum.org/EIPS/eip-191
+fn personal_ecrecover(input: &[u8]) -> Vec<u8> {
+    if input.len() < 65 {
+        return Vec::new();
+    }
+    let (signature, message) = input.split_at(65);
+    let mut prefixed_message = b"\x19Ethereum Signed Message:\n".to_vec();
+    prefixed_message.extend_from_slice(message.len().to_string().as_bytes());
+    prefixed_message.extend_from_slice(message);
+    match ecrecover(keccak(&prefixed_message), signature) {
+        Ok(signer) => {
+            let mut output = vec![0u8; 12];
+            output.extend_from_slice(signer.as_bytes());
+            output
+        }
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Writes a UTF-8 message to the NEAR transaction log. The message is logged
+/// right away, so it stays in the log even if the calling frame reverts.
+#[cfg(any(feature = "contract", feature = "standalone"))]
+fn near_log(input: &[u8], cost: u64, target_gas: Option<u64>) -> PrecompileResult {
+    if input.len() > MAX_NEAR_LOG_LENGTH {
+        return Err(ExitError::Other(Borrowed("ERR_NEAR_LOG_TOO_LONG")));
+    }
+    if core::str::from_utf8(input).is_err() {
+        return Err(ExitError::Other(Borrowed("ERR_INVALID_UTF8")));
+    }
+    charge(cost, target_gas, || {
+        crate::sdk::log_utf8(input);
+        Vec::new()
+    })
+}
+
 #[allow(dead_code)]
 pub(crate) fn ecverify(hash: H256, signature: &[u8], signer: Address) -> bool {
     matches!(ecrecover(hash, signature), Ok(s) if s == signer)
@@ -70,20 +264,31 @@ pub(crate) fn ecverify(hash: H256, signature: &[u8], signer: Address) -> bool {
 /// See: https://ethereum.github.io/yellowpaper/paper.pdf
 /// See: https://docs.soliditylang.org/en/develop/units-and-global-variables.html#mathematical-and-cryptographic-functions
 /// See: https://etherscan.io/address/0x0000000000000000000000000000000000000001
+/// Accepts the signature as (r, s, v) or in the 64-byte EIP-2098 compact form.
 #[allow(dead_code)]
 pub(crate) fn ecrecover(hash: H256, signature: &[u8]) -> Result<Address, ExitError> {
     use sha3::Digest;
-    assert_eq!(signature.len(), 65);
+    let signature = match signature.len() {
+        65 => {
+            let mut full_signature = [0u8; 65];
+            full_signature.copy_from_slice(signature);
+            full_signature
+        }
+        64 => expand_compact_signature(signature),
+        _ => return Err(ExitError::Other(Borrowed("invalid ECDSA signature"))),
+    };
 
-    let hash = secp256k1::Message::parse_slice(hash.as_bytes()).unwrap();
     let v = signature[64];
-    let signature = secp256k1::Signature::parse_slice(&signature[0..64]).unwrap();
     let bit = match v {
         0..=26 => v,
         _ => v - 27,
     };
 
-    if let Ok(recovery_id) = secp256k1::RecoveryId::parse(bit) {
+    if let (Ok(hash), Ok(signature), Ok(recovery_id)) = (
+        secp256k1::Message::parse_slice(hash.as_bytes()),
+        secp256k1::Signature::parse_slice(&signature[0..64]),
+        secp256k1::RecoveryId::parse(bit),
+    ) {
         if let Ok(public_key) = secp256k1::recover(&hash, &signature, &recovery_id) {
             // recover returns a 65-byte key, but addresses come from the raw 64-byte key
             let r = sha3::Keccak256::digest(&public_key.serialize()[1..]);
@@ -93,9 +298,21 @@ pub(crate) fn ecrecover(hash: H256, signature: &[u8]) -> Result<Address, ExitErr
     Err(ExitError::Other(Borrowed("invalid ECDSA signature")))
 }
 
+/// Expands a compact (r, yParityAndS) signature, which keeps the y parity in
+/// the top bit of s, into (r, s, v).
+/// See: https://eips.ethereum.org/EIPS/eip-2098
+fn expand_compact_signature(compact: &[u8]) -> [u8; 65] {
+    let mut signature = [0u8; 65];
+    signature[..64].copy_from_slice(compact);
+    signature[64] = 27 + (compact[32] >> 7);
+    signature[32] &= 0x7f;
+    signature
+}
+
 /// See: https://ethereum.github.io/yellowpaper/paper.pdf
 /// See: https://docs.soliditylang.org/en/develop/units-and-global-variables.html#mathematical-and-cryptographic-functions
 /// See: https://etherscan.io/address/0x0000000000000000000000000000000000000002
+/// The input is hashed in place, by the host function in the contract, without copying it.
 #[cfg(not(feature = "contract"))]
 fn sha256(input: &[u8]) -> H256 {
     use sha2::Digest;
@@ -176,6 +393,124 @@ mod tests {
         assert!(ecverify(hash, &signature, signer));
     }
 
+    #[test]
+    fn test_ecrecover_compact_signature() {
+        // web3.eth.accounts.sign("Some data", "0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318")
+        let hash = keccak(b"\x19Ethereum Signed Message:\n9Some data");
+        let signature = hex::decode("b91467e570a6466aa9e9876cbcd013baba02900b8979d43fe208a4a4f339f5fd6007e74cd82e037b800186422fc2da167c747ef045e5d18a5f5d4300f8e1a0291c").unwrap();
+        // v = 28, so the compact form sets the top bit of s.
+        let compact = hex::decode("b91467e570a6466aa9e9876cbcd013baba02900b8979d43fe208a4a4f339f5fde007e74cd82e037b800186422fc2da167c747ef045e5d18a5f5d4300f8e1a029").unwrap();
+        let signer =
+            Address::from_slice(&hex::decode("2c7536e3605d9c16a7a3d7b1898e529396a65c23").unwrap());
+        assert!(matches!(ecrecover(hash, &signature), Ok(s) if s == signer));
+        assert!(matches!(ecrecover(hash, &compact), Ok(s) if s == signer));
+        assert!(ecrecover(hash, &compact[..63]).is_err());
+    }
+
+    fn context() -> Context {
+        Context {
+            address: Address::zero(),
+            caller: Address::zero(),
+            apparent_value: U256::zero(),
+        }
+    }
+
+    #[test]
+    fn test_istanbul_costs() {
+        assert_eq!(ISTANBUL_COSTS, PrecompileCosts::default());
+        assert_eq!(ISTANBUL_COSTS.ecrecover.cost(128), 3000);
+        assert_eq!(ISTANBUL_COSTS.sha256.cost(0), 60);
+        assert_eq!(ISTANBUL_COSTS.sha256.cost(33), 60 + 2 * 12);
+        assert_eq!(ISTANBUL_COSTS.ripemd160.cost(32), 600 + 120);
+        assert_eq!(ISTANBUL_COSTS.identity.cost(64), 15 + 2 * 3);
+    }
+
+    #[test]
+    fn test_precompile_charges_gas() {
+        let address = Address::from_low_u64_be(4);
+        let result = istanbul_precompiles(address, &[1, 2, 3], Some(18), &context());
+        assert!(
+            matches!(result, Some(Ok((ExitSucceed::Returned, ref output, 18))) if output == &[1, 2, 3])
+        );
+        let result = istanbul_precompiles(address, &[1, 2, 3], Some(17), &context());
+        assert!(matches!(result, Some(Err(ExitError::OutOfGas))));
+    }
+
+    #[test]
+    fn test_ecrecover_raw() {
+        let input = hex::decode("1111111111111111111111111111111111111111111111111111111111111111000000000000000000000000000000000000000000000000000000000000001bb9f0bb08640d3c1c00761cdd0121209268f6fd3816bc98b9e6f3cc77bf82b69812ac7a61788a0fdc0e19180f14c945a8e1088a27d92a74dce81c0981fb644744").unwrap();
+        let signer =
+            Address::from_slice(&hex::decode("1563915e194D8CfBA1943570603F7606A3115508").unwrap());
+        assert_eq!(ecrecover_raw(&input), signer);
+        // Short input is padded with zeros rather than panicking.
+        assert_ne!(ecrecover_raw(&input[..100]), signer);
+        assert_eq!(ecrecover_raw(&[]), Address::zero());
+    }
+
+    #[test]
+    fn test_ecrecover_invalid_input() {
+        let address = Address::from_low_u64_be(1);
+        let input = hex::decode("1111111111111111111111111111111111111111111111111111111111111111000000000000000000000000000000000000000000000000000000000000001bb9f0bb08640d3c1c00761cdd0121209268f6fd3816bc98b9e6f3cc77bf82b69812ac7a61788a0fdc0e19180f14c945a8e1088a27d92a74dce81c0981fb644744").unwrap();
+        let recover = |input: &[u8]| match istanbul_precompiles(address, input, None, &context()) {
+            Some(Ok((ExitSucceed::Returned, output, 3000))) => output,
+            _ => panic!("ecrecover failed"),
+        };
+        let signer =
+            hex::decode("0000000000000000000000001563915e194D8CfBA1943570603F7606A3115508")
+                .unwrap();
+        assert_eq!(recover(&input), signer);
+
+        let with = |index: usize, byte: u8| {
+            let mut input = input.clone();
+            input[index] = byte;
+            input
+        };
+        let invalid_inputs = [
+            with(63, 29),                                         // v other than 27 or 28
+            with(63, 0),                                          // v as a bare recovery id
+            with(40, 1),                                          // v not fitting in a byte
+            [&input[..64], &[0u8; 32], &input[96..]].concat(),    // r = 0
+            [&input[..96], &[0u8; 32]].concat(),                  // s = 0
+            [&input[..64], &[0xffu8; 32], &input[96..]].concat(), // r above the curve order
+            input[..64].to_vec(),                                 // r and s missing
+            Vec::new(),
+        ];
+        for invalid_input in invalid_inputs.iter() {
+            assert_eq!(recover(invalid_input), vec![0u8; 32]);
+        }
+    }
+
+    #[test]
+    fn test_unimplemented_precompile_fails() {
+        let address = Address::from_low_u64_be(5);
+        let result = istanbul_precompiles(address, &[], None, &context());
+        assert!(matches!(result, Some(Err(ExitError::Other(_)))));
+    }
+
+    #[test]
+    fn test_ecrecover_invalid_signature_length() {
+        let hash = H256::from_slice(&[0x11u8; 32]);
+        assert!(ecrecover(hash, &[]).is_err());
+        assert!(ecrecover(hash, &[0u8; 64]).is_err());
+        assert!(ecrecover(hash, &[0u8; 66]).is_err());
+        // A valid signature cut short fails rather than panicking, as it used to:
+        let signature = hex::decode("b9f0bb08640d3c1c00761cdd0121209268f6fd3816bc98b9e6f3cc77bf82b69812ac7a61788a0fdc0e19180f14c945a8e1088a27d92a74dce81c0981fb6447441b").unwrap();
+        for len in [1, 32, 63].iter() {
+            assert!(ecrecover(hash, &signature[..*len]).is_err());
+        }
+    }
+
+    #[test]
+    fn test_ecrecover_out_of_range_v() {
+        let hash = H256::from_slice(&[0x11u8; 32]);
+        let signature = hex::decode("b9f0bb08640d3c1c00761cdd0121209268f6fd3816bc98b9e6f3cc77bf82b69812ac7a61788a0fdc0e19180f14c945a8e1088a27d92a74dce81c0981fb6447441b").unwrap();
+        assert!(ecrecover(hash, &signature).is_ok());
+        for v in [4, 26, 31, 255].iter() {
+            let signature = [&signature[..64], &[*v]].concat();
+            assert!(ecrecover(hash, &signature).is_err(), "{}", v);
+        }
+    }
+
     #[test]
     fn test_sha256() {
         assert_eq!(
@@ -187,6 +522,98 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_sha256_large_input() {
+        let address = Address::from_low_u64_be(2);
+        let input = [0xab; 5000];
+        let expected =
+            hex::decode("3fed102f96d368ff09416b8693e626070616d5c3f324a5d93341e9efe661c1e3")
+                .unwrap();
+        // 60 + 12 * ceil(5000 / 32)
+        let result = istanbul_precompiles(address, &input, Some(1944), &context());
+        assert!(
+            matches!(result, Some(Ok((ExitSucceed::Returned, ref output, 1944))) if output == &expected)
+        );
+        let result = istanbul_precompiles(address, &input, Some(1943), &context());
+        assert!(matches!(result, Some(Err(ExitError::OutOfGas))));
+    }
+
+    #[test]
+    fn test_personal_ecrecover() {
+        let address = Address::from_low_u64_be(PERSONAL_ECRECOVER_ADDRESS);
+        // web3.eth.accounts.sign("Some data", "0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318")
+        let signature = hex::decode("b91467e570a6466aa9e9876cbcd013baba02900b8979d43fe208a4a4f339f5fd6007e74cd82e037b800186422fc2da167c747ef045e5d18a5f5d4300f8e1a0291c").unwrap();
+        let input = [&signature[..], b"Some data"].concat();
+        let expected =
+            hex::decode("0000000000000000000000002c7536e3605d9c16a7a3d7b1898e529396a65c23")
+                .unwrap();
+        assert_eq!(
+            ISTANBUL_COSTS.personal_ecrecover.cost(input.len()),
+            3030 + 3 * 6
+        );
+        let result = istanbul_precompiles(address, &input, None, &context());
+        assert!(
+            matches!(result, Some(Ok((ExitSucceed::Returned, ref output, _))) if output == &expected)
+        );
+
+        // A different message recovers a different signer, a short input none.
+        let input = [&signature[..], b"Some other data"].concat();
+        let result = istanbul_precompiles(address, &input, None, &context());
+        assert!(matches!(result, Some(Ok((_, ref output, _))) if output != &expected));
+        let result = istanbul_precompiles(address, &signature[..64], None, &context());
+        assert!(matches!(result, Some(Ok((_, ref output, _))) if output.is_empty()));
+    }
+
+    #[cfg(feature = "standalone")]
+    #[test]
+    fn test_istanbul_precompiles_listed() {
+        crate::standalone::set_env(crate::standalone::Env::default());
+        for (address, _) in ISTANBUL_PRECOMPILES.iter() {
+            let address = Address::from_low_u64_be(*address);
+            let result = istanbul_precompiles(address, &[], None, &context());
+            assert!(matches!(result, Some(Ok(_))), "{:?}", address);
+        }
+        assert!(!ISTANBUL_PRECOMPILES
+            .iter()
+            .any(|(address, _)| *address == 5));
+    }
+
+    #[cfg(feature = "standalone")]
+    #[test]
+    fn test_near_log() {
+        use crate::standalone;
+
+        let address = Address::from_low_u64_be(NEAR_LOG_ADDRESS);
+        standalone::set_env(standalone::Env::default());
+        // 375 + 256 * ceil(5 / 32)
+        let result = istanbul_precompiles(address, b"hello", Some(631), &context());
+        assert!(
+            matches!(result, Some(Ok((ExitSucceed::Returned, ref output, 631))) if output.is_empty())
+        );
+        let result = istanbul_precompiles(address, b"hello", Some(630), &context());
+        assert!(matches!(result, Some(Err(ExitError::OutOfGas))));
+        let result = istanbul_precompiles(address, &[0xff], None, &context());
+        assert!(matches!(result, Some(Err(ExitError::Other(_)))));
+        assert_eq!(standalone::get_env().logs, vec!["hello".to_string()]);
+    }
+
+    #[cfg(feature = "standalone")]
+    #[test]
+    fn test_attached_deposit() {
+        use crate::standalone;
+
+        let address = Address::from_low_u64_be(ATTACHED_DEPOSIT_ADDRESS);
+        standalone::set_env(standalone::Env {
+            attached_deposit: 1_000_000_000_000_000_000_000_000,
+            ..standalone::Env::default()
+        });
+        let result = istanbul_precompiles(address, &[], None, &context());
+        let expected = 1_000_000_000_000_000_000_000_000u128.to_be_bytes();
+        assert!(
+            matches!(result, Some(Ok((ExitSucceed::Returned, ref output, 15))) if output == &expected)
+        );
+    }
+
     #[test]
     fn test_ripemd160() {
         assert_eq!(
@@ -18,3 +18,14 @@ pub type Address = H160;
 pub fn Address(input: [u8; 20]) -> Address {
     H160(input)
 }
+
+/// Logs `$message` if the engine state's log level enables `$level`; the
+/// message is only built when it is logged.
+#[macro_export]
+macro_rules! log {
+    ($state:expr, $level:expr, $message:expr) => {
+        if $state.log_level.enables($level) {
+            $crate::sdk::log($message)
+        }
+    };
+}
@@ -2,6 +2,10 @@ use crate::prelude::{vec, String, Vec, H256};
 use crate::types::STORAGE_PRICE_PER_BYTE;
 use borsh::{BorshDeserialize, BorshSerialize};
 
+#[cfg(feature = "standalone")]
+use crate::standalone::exports;
+
+#[cfg(not(feature = "standalone"))]
 mod exports {
 
     #[allow(unused)]
@@ -160,8 +164,8 @@ mod exports {
 pub fn read_input() -> Vec<u8> {
     unsafe {
         exports::input(0);
-        let bytes: Vec<u8> = vec![0; exports::register_len(0) as usize];
-        exports::read_register(0, bytes.as_ptr() as *const u64 as u64);
+        let mut bytes: Vec<u8> = vec![0; exports::register_len(0) as usize];
+        exports::read_register(0, bytes.as_mut_ptr() as *mut u64 as u64);
         bytes
     }
 }
@@ -170,13 +174,14 @@ pub fn read_input() -> Vec<u8> {
 pub fn read_input_arr20() -> [u8; 20] {
     unsafe {
         exports::input(0);
-        let bytes = [0u8; 20];
-        exports::read_register(0, bytes.as_ptr() as *const u64 as u64);
+        let mut bytes = [0u8; 20];
+        exports::read_register(0, bytes.as_mut_ptr() as *mut u64 as u64);
         bytes
     }
 }
 
 /// Reads current input and stores in the given key keeping data in the runtime.
+#[allow(dead_code)]
 pub fn read_input_and_store(key: &[u8]) {
     unsafe {
         exports::input(0);
@@ -196,8 +201,8 @@ pub fn return_output(value: &[u8]) {
 pub fn read_storage(key: &[u8]) -> Option<Vec<u8>> {
     unsafe {
         if exports::storage_read(key.len() as u64, key.as_ptr() as u64, 0) == 1 {
-            let bytes: Vec<u8> = vec![0u8; exports::register_len(0) as usize];
-            exports::read_register(0, bytes.as_ptr() as *const u64 as u64);
+            let mut bytes: Vec<u8> = vec![0u8; exports::register_len(0) as usize];
+            exports::read_register(0, bytes.as_mut_ptr() as *mut u64 as u64);
             Some(bytes)
         } else {
             None
@@ -206,11 +211,12 @@ pub fn read_storage(key: &[u8]) -> Option<Vec<u8>> {
 }
 
 /// Read u64 from storage at given key.
+#[allow(dead_code)]
 pub fn read_u64(key: &[u8]) -> Option<u64> {
     unsafe {
         if exports::storage_read(key.len() as u64, key.as_ptr() as u64, 0) == 1 {
-            let result = [0u8; 8];
-            exports::read_register(0, result.as_ptr() as _);
+            let mut result = [0u8; 8];
+            exports::read_register(0, result.as_mut_ptr() as _);
             Some(u64::from_le_bytes(result))
         } else {
             None
@@ -272,8 +278,8 @@ pub fn log_utf8(bytes: &[u8]) {
 pub fn predecessor_account_id() -> Vec<u8> {
     unsafe {
         exports::predecessor_account_id(1);
-        let bytes: Vec<u8> = vec![0u8; exports::register_len(1) as usize];
-        exports::read_register(1, bytes.as_ptr() as *const u64 as u64);
+        let mut bytes: Vec<u8> = vec![0u8; exports::register_len(1) as usize];
+        exports::read_register(1, bytes.as_mut_ptr() as *mut u64 as u64);
         bytes
     }
 }
@@ -283,8 +289,8 @@ pub fn predecessor_account_id() -> Vec<u8> {
 pub fn sha256(input: &[u8]) -> H256 {
     unsafe {
         exports::sha256(input.len() as u64, input.as_ptr() as u64, 1);
-        let bytes = H256::zero();
-        exports::read_register(1, bytes.0.as_ptr() as *const u64 as u64);
+        let mut bytes = H256::zero();
+        exports::read_register(1, bytes.0.as_mut_ptr() as *mut u64 as u64);
         bytes
     }
 }
@@ -294,8 +300,8 @@ pub fn sha256(input: &[u8]) -> H256 {
 pub fn keccak(input: &[u8]) -> H256 {
     unsafe {
         exports::keccak256(input.len() as u64, input.as_ptr() as u64, 1);
-        let bytes = H256::zero();
-        exports::read_register(1, bytes.0.as_ptr() as *const u64 as u64);
+        let mut bytes = H256::zero();
+        exports::read_register(1, bytes.0.as_mut_ptr() as *mut u64 as u64);
         bytes
     }
 }
@@ -309,17 +315,20 @@ pub fn panic_hex(data: &[u8]) -> ! {
 }
 
 /// Returns account id of the current account.
+#[allow(dead_code)]
 pub fn current_account_id() -> Vec<u8> {
     unsafe {
         exports::current_account_id(1);
-        let bytes: Vec<u8> = vec![0u8; exports::register_len(1) as usize];
-        exports::read_register(1, bytes.as_ptr() as *const u64 as u64);
+        let mut bytes: Vec<u8> = vec![0u8; exports::register_len(1) as usize];
+        exports::read_register(1, bytes.as_mut_ptr() as *mut u64 as u64);
         bytes
     }
 }
 
 /// Deploy code from given key in place of the current key.
-pub fn self_deploy(code_key: &[u8]) {
+/// Returns the index of the deployment's promise.
+#[allow(dead_code)]
+pub fn self_deploy(code_key: &[u8]) -> u64 {
     unsafe {
         // Load current account id into register 0.
         exports::current_account_id(0);
@@ -328,6 +337,7 @@ pub fn self_deploy(code_key: &[u8]) {
         // Remove code from storage and store it in register 1.
         exports::storage_remove(code_key.len() as _, code_key.as_ptr() as _, 1);
         exports::promise_batch_action_deploy_contract(promise_id, u64::MAX as _, 1);
+        promise_id
     }
 }
 
@@ -417,20 +427,29 @@ pub fn promise_results_count() -> u64 {
     unsafe { exports::promise_results_count() }
 }
 
-/*pub fn promise_result(result_idx: u64) -> PromiseResult {
+/// Outcome of a promise a callback was attached to.
+#[allow(dead_code)]
+pub enum PromiseResult {
+    NotReady,
+    Successful(Vec<u8>),
+    Failed,
+}
+
+#[allow(dead_code)]
+pub fn promise_result(result_idx: u64) -> PromiseResult {
     unsafe {
         match exports::promise_result(result_idx, 0) {
             0 => PromiseResult::NotReady,
             1 => {
-                let bytes: Vec<u8> = vec![0; exports::register_len(0) as usize];
-                exports::read_register(0, bytes.as_ptr() as *const u64 as u64);
+                let mut bytes: Vec<u8> = vec![0; exports::register_len(0) as usize];
+                exports::read_register(0, bytes.as_mut_ptr() as *mut u64 as u64);
                 PromiseResult::Successful(bytes)
             }
-            2 => PromiseResult::Failed,
-            _ => panic!("{}", RETURN_CODE_ERR),
+            // The runtime returns no other codes; treat any as a failure.
+            _ => PromiseResult::Failed,
         }
     }
-}*/
+}
 
 #[allow(dead_code)]
 pub fn assert_private_call() {
@@ -441,11 +460,12 @@ pub fn assert_private_call() {
     );
 }
 
+#[allow(dead_code)]
 pub fn attached_deposit() -> u128 {
-    use core::intrinsics::size_of;
+    use core::mem::size_of;
     unsafe {
-        let data = [0u8; size_of::<u128>()];
-        exports::attached_deposit(data.as_ptr() as u64);
+        let mut data = [0u8; size_of::<u128>()];
+        exports::attached_deposit(data.as_mut_ptr() as u64);
         u128::from_le_bytes(data)
     }
 }
@@ -0,0 +1,252 @@
+//! In-memory implementation of the NEAR host functions used by `sdk`, so that
+//! the engine can run off-chain as a plain Rust library (for simulation,
+//! fuzzing and fast tests).
+
+use crate::prelude::{HashMap, String, Vec};
+use std::cell::RefCell;
+
+/// Simulated NEAR runtime environment of the current thread.
+#[derive(Default, Clone)]
+pub struct Env {
+    /// Contract storage.
+    pub storage: HashMap<Vec<u8>, Vec<u8>>,
+    /// Account id of the current (engine) account.
+    pub current_account_id: Vec<u8>,
+    /// Account id of the caller.
+    pub predecessor_account_id: Vec<u8>,
+    /// Input of the current function call.
+    pub input: Vec<u8>,
+    pub block_index: u64,
+    /// Block timestamp in nanoseconds.
+    pub block_timestamp: u64,
+    /// Attached deposit in yoctoNEAR.
+    pub attached_deposit: u128,
+    pub prepaid_gas: u64,
+    /// Messages logged by the contract.
+    pub logs: Vec<String>,
+    /// Value returned by the last function call.
+    pub return_value: Option<Vec<u8>>,
+    registers: HashMap<u64, Vec<u8>>,
+}
+
+thread_local! {
+    static ENV: RefCell<Env> = RefCell::new(Env::default());
+}
+
+/// Runs the given closure with mutable access to the current thread's environment.
+pub fn with_env<T, F: FnOnce(&mut Env) -> T>(f: F) -> T {
+    ENV.with(|env| f(&mut env.borrow_mut()))
+}
+
+/// Replaces the current thread's environment.
+pub fn set_env(env: Env) {
+    with_env(|current| *current = env)
+}
+
+/// Returns a copy of the current thread's environment.
+pub fn get_env() -> Env {
+    with_env(|env| env.clone())
+}
+
+pub(crate) mod exports {
+    use super::with_env;
+    use crate::prelude::{String, Vec};
+
+    /// Reads `len` bytes at `ptr`, or the contents of register `ptr` when `len`
+    /// is `u64::MAX`, as the NEAR runtime does.
+    unsafe fn read_bytes(len: u64, ptr: u64) -> Vec<u8> {
+        if len == u64::MAX {
+            with_env(|env| env.registers.get(&ptr).cloned().unwrap_or_default())
+        } else {
+            core::slice::from_raw_parts(ptr as *const u8, len as usize).to_vec()
+        }
+    }
+
+    fn write_register(register_id: u64, data: Vec<u8>) {
+        with_env(|env| env.registers.insert(register_id, data));
+    }
+
+    // #############
+    // # Registers #
+    // #############
+    pub(crate) unsafe fn read_register(register_id: u64, ptr: u64) {
+        let data =
+            with_env(|env| env.registers.get(&register_id).cloned()).expect("ERR_INVALID_REGISTER");
+        core::ptr::copy_nonoverlapping(data.as_ptr(), ptr as *mut u8, data.len());
+    }
+    pub(crate) unsafe fn register_len(register_id: u64) -> u64 {
+        with_env(|env| {
+            env.registers
+                .get(&register_id)
+                .map(|data| data.len() as u64)
+        })
+        .unwrap_or(u64::MAX)
+    }
+    // ###############
+    // # Context API #
+    // ###############
+    pub(crate) unsafe fn current_account_id(register_id: u64) {
+        write_register(register_id, with_env(|env| env.current_account_id.clone()));
+    }
+    pub(crate) unsafe fn predecessor_account_id(register_id: u64) {
+        write_register(
+            register_id,
+            with_env(|env| env.predecessor_account_id.clone()),
+        );
+    }
+    pub(crate) unsafe fn input(register_id: u64) {
+        write_register(register_id, with_env(|env| env.input.clone()));
+    }
+    pub(crate) unsafe fn block_index() -> u64 {
+        with_env(|env| env.block_index)
+    }
+    pub(crate) unsafe fn block_timestamp() -> u64 {
+        with_env(|env| env.block_timestamp)
+    }
+    pub(crate) unsafe fn storage_usage() -> u64 {
+        with_env(|env| {
+            env.storage
+                .iter()
+                .map(|(key, value)| (key.len() + value.len()) as u64)
+                .sum()
+        })
+    }
+    // #################
+    // # Economics API #
+    // #################
+    pub(crate) unsafe fn attached_deposit(balance_ptr: u64) {
+        let deposit = with_env(|env| env.attached_deposit).to_le_bytes();
+        core::ptr::copy_nonoverlapping(deposit.as_ptr(), balance_ptr as *mut u8, deposit.len());
+    }
+    pub(crate) unsafe fn prepaid_gas() -> u64 {
+        with_env(|env| env.prepaid_gas)
+    }
+    // ############
+    // # Math API #
+    // ############
+    pub(crate) unsafe fn sha256(value_len: u64, value_ptr: u64, register_id: u64) {
+        use sha2::Digest;
+        let hash = sha2::Sha256::digest(&read_bytes(value_len, value_ptr));
+        write_register(register_id, hash.to_vec());
+    }
+    pub(crate) unsafe fn keccak256(value_len: u64, value_ptr: u64, register_id: u64) {
+        use sha3::Digest;
+        let hash = sha3::Keccak256::digest(&read_bytes(value_len, value_ptr));
+        write_register(register_id, hash.to_vec());
+    }
+    // #####################
+    // # Miscellaneous API #
+    // #####################
+    pub(crate) unsafe fn value_return(value_len: u64, value_ptr: u64) {
+        let value = read_bytes(value_len, value_ptr);
+        with_env(|env| env.return_value = Some(value));
+    }
+    pub(crate) unsafe fn panic() {
+        panic!("explicit guest panic")
+    }
+    pub(crate) unsafe fn panic_utf8(len: u64, ptr: u64) {
+        panic!("{}", String::from_utf8_lossy(&read_bytes(len, ptr)))
+    }
+    pub(crate) unsafe fn log_utf8(len: u64, ptr: u64) {
+        let message = String::from_utf8_lossy(&read_bytes(len, ptr)).into_owned();
+        with_env(|env| env.logs.push(message));
+    }
+    // ################
+    // # Promises API #
+    // ################
+    // Promises are not executed off-chain: they are accepted and dropped.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) unsafe fn promise_create(
+        _account_id_len: u64,
+        _account_id_ptr: u64,
+        _method_name_len: u64,
+        _method_name_ptr: u64,
+        _arguments_len: u64,
+        _arguments_ptr: u64,
+        _amount_ptr: u64,
+        _gas: u64,
+    ) -> u64 {
+        0
+    }
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) unsafe fn promise_then(
+        _promise_index: u64,
+        _account_id_len: u64,
+        _account_id_ptr: u64,
+        _method_name_len: u64,
+        _method_name_ptr: u64,
+        _arguments_len: u64,
+        _arguments_ptr: u64,
+        _amount_ptr: u64,
+        _gas: u64,
+    ) -> u64 {
+        0
+    }
+    pub(crate) unsafe fn promise_batch_create(_account_id_len: u64, _account_id_ptr: u64) -> u64 {
+        0
+    }
+    // #######################
+    // # Promise API actions #
+    // #######################
+    pub(crate) unsafe fn promise_batch_action_deploy_contract(
+        _promise_index: u64,
+        _code_len: u64,
+        _code_ptr: u64,
+    ) {
+    }
+    pub(crate) unsafe fn promise_batch_action_transfer(_promise_index: u64, _amount_ptr: u64) {}
+    // #######################
+    // # Promise API results #
+    // #######################
+    pub(crate) unsafe fn promise_results_count() -> u64 {
+        0
+    }
+    pub(crate) unsafe fn promise_result(_result_idx: u64, _register_id: u64) -> u64 {
+        0
+    }
+    pub(crate) unsafe fn promise_return(_promise_id: u64) {}
+    // ###############
+    // # Storage API #
+    // ###############
+    pub(crate) unsafe fn storage_write(
+        key_len: u64,
+        key_ptr: u64,
+        value_len: u64,
+        value_ptr: u64,
+        register_id: u64,
+    ) -> u64 {
+        let key = read_bytes(key_len, key_ptr);
+        let value = read_bytes(value_len, value_ptr);
+        match with_env(|env| env.storage.insert(key, value)) {
+            Some(evicted) => {
+                write_register(register_id, evicted);
+                1
+            }
+            None => 0,
+        }
+    }
+    pub(crate) unsafe fn storage_read(key_len: u64, key_ptr: u64, register_id: u64) -> u64 {
+        let key = read_bytes(key_len, key_ptr);
+        match with_env(|env| env.storage.get(&key).cloned()) {
+            Some(value) => {
+                write_register(register_id, value);
+                1
+            }
+            None => 0,
+        }
+    }
+    pub(crate) unsafe fn storage_remove(key_len: u64, key_ptr: u64, register_id: u64) -> u64 {
+        let key = read_bytes(key_len, key_ptr);
+        match with_env(|env| env.storage.remove(&key)) {
+            Some(evicted) => {
+                write_register(register_id, evicted);
+                1
+            }
+            None => 0,
+        }
+    }
+    pub(crate) unsafe fn storage_has_key(key_len: u64, key_ptr: u64) -> u64 {
+        let key = read_bytes(key_len, key_ptr);
+        with_env(|env| env.storage.contains_key(&key)) as u64
+    }
+}
@@ -1,5 +1,8 @@
 use crate::prelude::{Address, H256};
 
+/// The first byte of every key the engine stores per account or slot.
+/// Values from 0x7 up are reserved for future versioned keys, so new prefixes
+/// count down from 0xff.
 #[allow(dead_code)]
 pub enum KeyPrefix {
     Config = 0x0,
@@ -7,6 +10,9 @@ pub enum KeyPrefix {
     Balance = 0x2,
     Code = 0x3,
     Storage = 0x4,
+    BlockHash = 0x5,
+    RateLimit = 0x6,
+    BalanceHistory = 0xff,
 }
 
 #[allow(dead_code)]
@@ -26,5 +32,13 @@ pub fn storage_to_key(address: &Address, key: &H256) -> [u8; 53] {
     result
 }
 
+#[allow(dead_code)]
+pub fn block_hash_to_key(slot: u64) -> [u8; 9] {
+    let mut result = [0u8; 9];
+    result[0] = KeyPrefix::BlockHash as u8;
+    result[1..].copy_from_slice(&slot.to_be_bytes());
+    result
+}
+
 #[cfg(test)]
 mod tests {}
@@ -1,5 +1,5 @@
 use crate::precompiles::ecrecover;
-use crate::prelude::{Address, Vec, U256};
+use crate::prelude::{Address, Vec, H256, U256};
 use crate::types::keccak;
 use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
 
@@ -37,6 +37,44 @@ impl EthTransaction {
             s.append(&0u8);
         }
     }
+
+    pub fn kind(&self) -> TxKind {
+        match (self.to, self.data.is_empty()) {
+            (None, _) => TxKind::ContractCreation,
+            (Some(_), true) => TxKind::Transfer,
+            (Some(_), false) => TxKind::ContractCall,
+        }
+    }
+
+    /// Returns the intrinsic gas of the transaction under the given hardfork
+    /// configuration, or `None` on overflow. Non-zero calldata bytes cost 68 gas
+    /// before Istanbul and 16 gas since (EIP-2028).
+    pub fn intrinsic_gas(&self, config: &evm::Config) -> Option<u64> {
+        let base_gas = if self.to.is_some() {
+            config.gas_transaction_call
+        } else {
+            config.gas_transaction_create
+        };
+        let num_zero_bytes = self.data.iter().filter(|b| **b == 0).count() as u64;
+        let num_non_zero_bytes = self.data.len() as u64 - num_zero_bytes;
+        let zero_bytes_gas = num_zero_bytes.checked_mul(config.gas_transaction_zero_data)?;
+        let non_zero_bytes_gas =
+            num_non_zero_bytes.checked_mul(config.gas_transaction_non_zero_data)?;
+        base_gas
+            .checked_add(zero_bytes_gas)?
+            .checked_add(non_zero_bytes_gas)
+    }
+}
+
+/// What executing a transaction does, as told by its receiver and data.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TxKind {
+    /// A value transfer: a receiver and no data.
+    Transfer,
+    /// A contract call: a receiver and data.
+    ContractCall,
+    /// A contract deployment: no receiver.
+    ContractCreation,
 }
 
 #[derive(Debug, Eq, PartialEq)]
@@ -51,10 +89,26 @@ pub struct EthSignedTransaction {
     pub s: U256,
 }
 
+/// Half of the order of the secp256k1 curve, the largest `s` EIP-2 allows.
+const SECP256K1_HALF_ORDER: [u8; 32] = [
+    0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0x5d, 0x57, 0x6e, 0x73, 0x57, 0xa4, 0x50, 0x1d, 0xdf, 0xe9, 0x2f, 0x46, 0x68, 0x1b, 0x20, 0xa0,
+];
+
 impl EthSignedTransaction {
+    /// Checks that `s` is in the lower half of the curve order, as EIP-2 requires
+    /// so that a signed transaction has a single valid encoding.
+    pub fn has_low_s(&self) -> bool {
+        self.s <= U256::from(SECP256K1_HALF_ORDER)
+    }
+
     /// Returns sender of given signed transaction by doing ecrecover on the signature.
+    /// Signatures with a high `s` (see `has_low_s`) have no sender.
     #[allow(dead_code)]
     pub fn sender(&self) -> Option<Address> {
+        if !self.has_low_s() {
+            return None;
+        }
         let mut rlp_stream = RlpStream::new();
         // See details of CHAIN_ID computation here - https://github.com/ethereum/EIPs/blob/master/EIPS/eip-155.md#specification
         let (chain_id, rec_id) = match self.v {
@@ -138,6 +192,25 @@ impl Decodable for EthSignedTransaction {
     }
 }
 
+/// Returns the canonical hash of an encoded signed transaction: the keccak-256
+/// hash of its full signed RLP encoding (for typed transactions, of the
+/// type-prefixed payload), exactly as submitted.
+pub fn tx_hash(bytes: &[u8]) -> H256 {
+    keccak(bytes)
+}
+
+/// Classifies an RLP-encoded signed transaction without recovering its sender.
+pub fn classify(bytes: &[u8]) -> Result<TxKind, DecoderError> {
+    let signed_transaction = EthSignedTransaction::decode(&Rlp::new(bytes))?;
+    Ok(signed_transaction.transaction.kind())
+}
+
+/// Re-encodes a decoded signed transaction to RLP. For a transaction decoded
+/// from canonical RLP this reproduces the decoded bytes, and so their hash.
+pub fn encode(signed_transaction: &EthSignedTransaction) -> Vec<u8> {
+    rlp::encode(signed_transaction).to_vec()
+}
+
 fn vrs_to_arr(v: u8, r: U256, s: U256) -> [u8; 65] {
     let mut result = [0u8; 65]; // (r, s, v), typed (uint256, uint256, uint8)
     r.to_big_endian(&mut result[0..32]);
@@ -187,6 +260,145 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_intrinsic_gas() {
+        let transaction = EthTransaction {
+            nonce: U256::zero(),
+            gas_price: U256::zero(),
+            gas: U256::zero(),
+            to: Some(Address::zero()),
+            value: U256::zero(),
+            data: vec![0, 1, 2],
+        };
+        // 21000 + 1 zero byte * 4 + 2 non-zero bytes * 68
+        assert_eq!(
+            transaction.intrinsic_gas(&evm::Config::frontier()),
+            Some(21140)
+        );
+        // 21000 + 1 zero byte * 4 + 2 non-zero bytes * 16 (EIP-2028)
+        assert_eq!(
+            transaction.intrinsic_gas(&evm::Config::istanbul()),
+            Some(21036)
+        );
+    }
+
+    #[test]
+    fn test_intrinsic_gas_create() {
+        let transaction = EthTransaction {
+            nonce: U256::zero(),
+            gas_price: U256::zero(),
+            gas: U256::zero(),
+            to: None,
+            value: U256::zero(),
+            data: vec![1],
+        };
+        assert_eq!(
+            transaction.intrinsic_gas(&evm::Config::istanbul()),
+            Some(53016)
+        );
+    }
+
+    #[test]
+    fn test_decode_truncated_transaction() {
+        let encoded_tx = hex::decode("f86a8086d55698372431831e848094f0109fc8df283027b6285cc889f5aa624eac1f55843b9aca008025a009ebb6ca057a0535d6186462bc0b465b561c94a295bdb0621fc19208ab149a9ca0440ffd775ce91a833ab410777204d5341a6f9fa91216a6f3ee2c051fea6a0428").unwrap();
+        for len in 0..encoded_tx.len() {
+            assert!(EthSignedTransaction::decode(&Rlp::new(&encoded_tx[..len])).is_err());
+        }
+    }
+
+    #[test]
+    fn test_sender_invalid_v() {
+        let encoded_tx = hex::decode("f86a8086d55698372431831e848094f0109fc8df283027b6285cc889f5aa624eac1f55843b9aca008025a009ebb6ca057a0535d6186462bc0b465b561c94a295bdb0621fc19208ab149a9ca0440ffd775ce91a833ab410777204d5341a6f9fa91216a6f3ee2c051fea6a0428").unwrap();
+        let mut tx = EthSignedTransaction::decode(&Rlp::new(&encoded_tx)).unwrap();
+        let signer = tx.sender().unwrap();
+        for v in &[4, 26, 29, 34] {
+            tx.v = *v;
+            assert_eq!(tx.sender(), None);
+        }
+        // A v encoding an absurd chain id still recovers without panicking,
+        // though not the signer, who signed for chain 1.
+        for v in &[u64::max_value() - 1, u64::max_value()] {
+            tx.v = *v;
+            assert!(tx.chain_id().is_some());
+            assert_ne!(tx.sender(), Some(signer));
+        }
+    }
+
+    #[test]
+    fn test_sender_high_s() {
+        let encoded_tx = hex::decode("f86a8086d55698372431831e848094f0109fc8df283027b6285cc889f5aa624eac1f55843b9aca008025a009ebb6ca057a0535d6186462bc0b465b561c94a295bdb0621fc19208ab149a9ca0440ffd775ce91a833ab410777204d5341a6f9fa91216a6f3ee2c051fea6a0428").unwrap();
+        let mut tx = EthSignedTransaction::decode(&Rlp::new(&encoded_tx)).unwrap();
+        assert!(tx.has_low_s());
+        // The malleated signature: (r, n - s) with the other y parity.
+        let order = U256::from_big_endian(
+            &hex::decode("fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141")
+                .unwrap(),
+        );
+        tx.s = order - tx.s;
+        tx.v = 38;
+        assert!(!tx.has_low_s());
+        assert_eq!(tx.sender(), None);
+    }
+
+    #[test]
+    fn test_encode_roundtrip() {
+        let encoded_tx = hex::decode("f86a8086d55698372431831e848094f0109fc8df283027b6285cc889f5aa624eac1f55843b9aca008025a009ebb6ca057a0535d6186462bc0b465b561c94a295bdb0621fc19208ab149a9ca0440ffd775ce91a833ab410777204d5341a6f9fa91216a6f3ee2c051fea6a0428").unwrap();
+        let tx = EthSignedTransaction::decode(&Rlp::new(&encoded_tx)).unwrap();
+        assert_eq!(encode(&tx), encoded_tx);
+
+        // Decoding the encoding of any transaction gives it back, encoding the same:
+        let receivers = [None, Some(Address::zero()), Some(Address([0xff; 20]))];
+        for (i, receiver) in receivers.iter().enumerate() {
+            for data_len in [0, 1, 55, 56, 1024].iter() {
+                for number in [0, 1, 0x7f, 0x80, u64::max_value()].iter() {
+                    let tx = EthSignedTransaction {
+                        transaction: EthTransaction {
+                            nonce: U256::from(*number),
+                            gas_price: U256::from(*number) * U256::from(i),
+                            gas: U256::from(*data_len),
+                            to: *receiver,
+                            value: U256::max_value() - U256::from(*number),
+                            data: vec![*number as u8; *data_len],
+                        },
+                        v: 27 + number % 2,
+                        r: U256::from(*number),
+                        s: U256::from(*number) << 128,
+                    };
+                    let encoded_tx = encode(&tx);
+                    let decoded_tx = EthSignedTransaction::decode(&Rlp::new(&encoded_tx)).unwrap();
+                    assert_eq!(encode(&decoded_tx), encoded_tx);
+                    assert_eq!(decoded_tx, tx);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_tx_hash() {
+        let encoded_tx = hex::decode("f86a8086d55698372431831e848094f0109fc8df283027b6285cc889f5aa624eac1f55843b9aca008025a009ebb6ca057a0535d6186462bc0b465b561c94a295bdb0621fc19208ab149a9ca0440ffd775ce91a833ab410777204d5341a6f9fa91216a6f3ee2c051fea6a0428").unwrap();
+        assert_eq!(
+            hex::encode(tx_hash(&encoded_tx)),
+            "d8f64a42b57be0d565f385378db2f6bf324ce14a594afc05de90436e9ce01f60"
+        );
+    }
+
+    #[test]
+    fn test_classify() {
+        let transfer = hex::decode("f86a8086d55698372431831e848094f0109fc8df283027b6285cc889f5aa624eac1f55843b9aca008025a009ebb6ca057a0535d6186462bc0b465b561c94a295bdb0621fc19208ab149a9ca0440ffd775ce91a833ab410777204d5341a6f9fa91216a6f3ee2c051fea6a0428").unwrap();
+        assert_eq!(classify(&transfer), Ok(TxKind::Transfer));
+
+        let mut tx = EthSignedTransaction::decode(&Rlp::new(&transfer)).unwrap();
+        tx.transaction.data = vec![1];
+        assert_eq!(classify(&rlp::encode(&tx)), Ok(TxKind::ContractCall));
+        tx.transaction.to = None;
+        assert_eq!(classify(&rlp::encode(&tx)), Ok(TxKind::ContractCreation));
+        // A deployment of empty code is still a deployment.
+        tx.transaction.data = vec![];
+        assert_eq!(classify(&rlp::encode(&tx)), Ok(TxKind::ContractCreation));
+
+        assert!(classify(&transfer[..transfer.len() - 1]).is_err());
+    }
+
     fn address_from_arr(arr: &[u8]) -> Address {
         assert_eq!(arr.len(), 20);
         let mut address = [0u8; 20];
@@ -32,11 +32,95 @@ pub enum ErrorKind {
     ArgumentParseError,
     InvalidMetaTransactionMethodName,
     InvalidMetaTransactionFunctionArg,
+    MetaTransactionTooNested,
     InvalidEcRecoverSignature,
+    ViewInputTooLarge,
+    OutputTooLarge,
+    InsufficientBalance,
+    RateLimited,
+    BlockOutOfRange,
+    InvalidMaxLogsPerTransaction,
+    InvalidMaxReturnSize,
+    NoUpgrade,
+    UpgradeTooEarly,
+    UpgradeCodeMismatch,
 }
 
 pub type Result<T> = core::result::Result<T, ErrorKind>;
 
+/// Stable numeric codes of the `ERR_*` messages the engine fails with, so that
+/// clients can branch on an integer rather than on the message.
+/// Append only: a code is never reassigned, even after its error is removed.
+pub const ERROR_CODES: &[(u16, &str)] = &[
+    (1, "ERR_ARG_PARSE"),
+    (2, "ERR_SER"),
+    (3, "ERR_DESER"),
+    (4, "ERR_NOT_ALLOWED"),
+    (5, "ERR_NOT_ALLOWED:TOO_EARLY"),
+    (6, "ERR_NO_UPGRADE"),
+    (7, "ERR_INVALID_TX"),
+    (8, "ERR_INVALID_CHAIN_ID"),
+    (9, "ERR_INVALID_ECDSA_SIGNATURE"),
+    (10, "ERR_META_TX_PARSE"),
+    (11, "ERR_VIEW_INPUT_TOO_LARGE"),
+    (12, "ERR_GENESIS_SUPPLY_OVERFLOW"),
+    (13, "ERR_GENESIS_SUPPLY_MISMATCH"),
+    (14, "ERR_INTRINSIC_GAS"),
+    (15, "ERR_EMPTY_DEPLOY_CODE"),
+    (16, "ERR_PRECOMPILE_PANIC"),
+    (17, "ERR_PRECOMPILE_NOT_IMPLEMENTED"),
+    (18, "ERR_CODES_TOO_LARGE"),
+    (19, "ERR_INCORRECT_NONCE"),
+    (20, "ERR_TOO_MANY_LOGS"),
+    (21, "ERR_INVALID_TYPED_DATA"),
+    (22, "ERR_CHAIN_ID_REQUIRED"),
+    (23, "ERR_INVALID_ACCOUNT_ID"),
+    (24, "ERR_EMPTY_UPGRADE_CODE"),
+    (25, "ERR_UPGRADE_CODE_MISMATCH"),
+    (26, "ERR_CODE_SIZE_EXCEEDED"),
+    (27, "ERR_DERIVATION_SCHEME_CHANGE"),
+    (28, "ERR_OUT_OF_GAS"),
+    (29, "ERR_META_TX_TOO_NESTED"),
+    (30, "ERR_GAS_PRICE_TOO_LOW"),
+    (31, "ERR_TOO_MANY_STORAGE_SLOTS"),
+    (32, "ERR_RESULT_TOO_LARGE"),
+    (33, "ERR_NEAR_LOG_TOO_LONG"),
+    (34, "ERR_INVALID_UTF8"),
+    (35, "ERR_SEND_TO_ZERO"),
+    (36, "ERR_INSUFFICIENT_BALANCE"),
+    (37, "ERR_PARSE_NEW_ARGS"),
+    (38, "ERR_PARSE_RECOMPUTE_TOTAL_BALANCE_ARGS"),
+    (39, "ERR_PARSE_BURN_ARGS"),
+    (40, "ERR_PARSE_FUNCTION_CALL_ARGS"),
+    (41, "ERR_PARSE_VIEW_ARGS"),
+    (42, "ERR_PARSE_GET_STORAGE_AT_ARGS"),
+    (43, "ERR_PARSE_GET_STORAGE_SLOTS_ARGS"),
+    (44, "ERR_PARSE_GET_CODES_ARGS"),
+    (45, "ERR_PARSE_COMPUTE_STRUCT_HASH_ARGS"),
+    (46, "ERR_PARSE_BEGIN_CHAIN_ARGS"),
+    (47, "ERR_PARSE_BEGIN_BLOCK_ARGS"),
+    (48, "ERR_PARSE_ACCOUNT_BALANCE"),
+    (49, "ERR_PARSE_SET_CHAIN_ID_ARGS"),
+    (50, "ERR_CHAIN_ID_CHANGE_UNCONFIRMED"),
+    (51, "ERR_CONTRACT_ALREADY_EXISTS"),
+    (52, "ERR_RATE_LIMITED"),
+    (53, "ERR_PARSE_GET_BALANCE_AT_ARGS"),
+    (54, "ERR_BLOCK_OUT_OF_RANGE"),
+    (55, "ERR_INVALID_S_VALUE"),
+    (56, "ERR_NO_CODE_AT_TARGET"),
+    (57, "ERR_INVALID_MAX_LOGS_PER_TRANSACTION"),
+    (58, "ERR_INVALID_MAX_RETURN_SIZE"),
+    (59, "ERR_INVALID_NEW_ARGS"),
+];
+
+/// Returns the stable code of the given error message, if it has one.
+pub fn error_code(message: &str) -> Option<u16> {
+    ERROR_CODES
+        .iter()
+        .find(|(_, m)| *m == message)
+        .map(|(code, _)| *code)
+}
+
 #[allow(dead_code)]
 pub fn u256_to_arr(value: &U256) -> [u8; 32] {
     let mut result = [0u8; 32];
@@ -86,10 +170,71 @@ pub fn near_account_to_evm_address(addr: &[u8]) -> Address {
     Address::from_slice(&keccak(addr)[12..])
 }
 
+/// Checks that `account_id` is a well-formed NEAR account id: 2 to 64 characters
+/// of lowercase letters, digits, and `-`, `_` or `.` separators, where a separator
+/// neither starts nor ends the id nor follows another separator.
+pub fn is_valid_account_id(account_id: &[u8]) -> bool {
+    if account_id.len() < 2 || account_id.len() > 64 {
+        return false;
+    }
+    // Treat the start as following a separator, so a leading one is rejected.
+    let mut last_was_separator = true;
+    for c in account_id {
+        match c {
+            b'a'..=b'z' | b'0'..=b'9' => last_was_separator = false,
+            b'-' | b'_' | b'.' if !last_was_separator => last_was_separator = true,
+            _ => return false,
+        }
+    }
+    !last_was_separator
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_error_codes_are_unique() {
+        for (i, (code, message)) in ERROR_CODES.iter().enumerate() {
+            for (other_code, other_message) in &ERROR_CODES[i + 1..] {
+                assert_ne!(code, other_code);
+                assert_ne!(message, other_message);
+            }
+        }
+    }
+
+    #[test]
+    fn test_error_codes_cover_engine_errors() {
+        let sources = [
+            include_str!("engine.rs"),
+            include_str!("lib.rs"),
+            include_str!("meta_parsing.rs"),
+            include_str!("parameters.rs"),
+            include_str!("precompiles.rs"),
+            include_str!("transaction.rs"),
+        ];
+        for source in sources.iter() {
+            for message in source.split('"').filter(|s| s.starts_with("ERR_")) {
+                assert!(error_code(message).is_some(), "{} has no code", message);
+            }
+        }
+    }
+
+    #[test]
+    fn test_is_valid_account_id() {
+        for account_id in ["aurora", "prover.bridge.near", "a-b_c.0x", "ab"].iter() {
+            assert!(is_valid_account_id(account_id.as_bytes()), "{}", account_id);
+        }
+        for account_id in ["", "a", ".near", "near.", "a..b", "a-.b", "Aurora", "a b"].iter() {
+            assert!(
+                !is_valid_account_id(account_id.as_bytes()),
+                "{}",
+                account_id
+            );
+        }
+        assert!(!is_valid_account_id(&[b'a'; 65]));
+    }
+
     #[test]
     fn test_hex() {
         assert_eq!(
@@ -0,0 +1,108 @@
+//! Fixtures shared by the integration tests, which run the release build of
+//! the engine in the NEAR simulator.
+#![allow(dead_code)]
+
+use near_crypto::{InMemorySigner, KeyType, Signature, Signer};
+use near_sdk::borsh::BorshSerialize;
+use near_sdk::test_utils::accounts;
+use near_sdk_sim::{to_yocto, UserAccount, DEFAULT_GAS, STORAGE_AMOUNT};
+use rlp::RlpStream;
+
+use aurora_engine::parameters::NewCallArgs;
+use aurora_engine::prelude::{Address, U256};
+use aurora_engine::transaction::{encode, EthSignedTransaction, EthTransaction};
+use aurora_engine::types::keccak;
+
+near_sdk_sim::lazy_static_include::lazy_static_include_bytes! {
+    EVM_WASM_BYTES => "release.wasm"
+}
+
+/// The code of the engine under test.
+pub fn evm_wasm_bytes() -> &'static [u8] {
+    *EVM_WASM_BYTES
+}
+
+/// The `new` arguments the tests initialize the engine with: the defaults,
+/// with `owner_id` as the owner.
+pub fn new_call_args(owner_id: &str) -> NewCallArgs {
+    NewCallArgs {
+        owner_id: owner_id.to_string(),
+        bridge_prover_id: accounts(0).to_string(),
+        upgrade_delay_blocks: 1,
+        ..NewCallArgs::default()
+    }
+}
+
+/// Deploys the engine to `accounts(0)` and initializes it with the arguments
+/// of `new_call_args`, owned by the master account.
+pub fn init() -> (UserAccount, UserAccount) {
+    init_with(|_| {})
+}
+
+/// Like `init`, with the `new` arguments adjusted by `configure`.
+pub fn init_with(configure: impl FnOnce(&mut NewCallArgs)) -> (UserAccount, UserAccount) {
+    let (master_account, contract_account) = deploy();
+    let mut args = new_call_args(&master_account.account_id);
+    configure(&mut args);
+    contract_account
+        .call(
+            accounts(0).to_string(),
+            "new",
+            &args.try_to_vec().unwrap(),
+            DEFAULT_GAS,
+            STORAGE_AMOUNT,
+        )
+        .assert_success();
+    (master_account, contract_account)
+}
+
+/// Deploys the engine to `accounts(0)` without initializing it.
+pub fn deploy() -> (UserAccount, UserAccount) {
+    let master_account = near_sdk_sim::init_simulator(None);
+    let contract_account =
+        master_account.deploy(evm_wasm_bytes(), accounts(0).to_string(), to_yocto("1000"));
+    (master_account, contract_account)
+}
+
+/// A secp256k1 signer of Ethereum transactions.
+pub fn eth_signer() -> InMemorySigner {
+    InMemorySigner::from_seed("doesnt", KeyType::SECP256K1, "a")
+}
+
+/// A value transfer of nothing to `0x0101...01`, with enough gas for it.
+pub fn transfer(nonce: u64, gas_price: u64) -> EthTransaction {
+    EthTransaction {
+        nonce: U256::from(nonce),
+        gas_price: U256::from(gas_price),
+        gas: U256::from(21_000),
+        to: Some(Address([1u8; 20])),
+        value: U256::zero(),
+        data: Vec::new(),
+    }
+}
+
+/// Signs `transaction` for the given chain id (EIP-155), or for none, and
+/// returns the RLP encoding of the signed transaction.
+pub fn sign_transaction(
+    signer: &InMemorySigner,
+    transaction: EthTransaction,
+    chain_id: Option<u64>,
+) -> Vec<u8> {
+    let mut stream = RlpStream::new();
+    transaction.rlp_append_unsigned(&mut stream, chain_id);
+    let signature: [u8; 65] = match signer.sign(keccak(stream.as_raw()).as_bytes()) {
+        Signature::SECP256K1(signature) => signature.into(),
+        Signature::ED25519(_) => panic!("Wrong Signer"),
+    };
+    let recovery_id = u64::from(signature[64]);
+    let v = match chain_id {
+        Some(chain_id) => 35 + 2 * chain_id + recovery_id,
+        None => 27 + recovery_id,
+    };
+    encode(&EthSignedTransaction {
+        transaction,
+        v,
+        r: U256::from_big_endian(&signature[..32]),
+        s: U256::from_big_endian(&signature[32..64]),
+    })
+}
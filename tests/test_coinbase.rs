@@ -0,0 +1,46 @@
+use near_sdk::borsh::BorshSerialize;
+use near_sdk::test_utils::accounts;
+use near_sdk_sim::DEFAULT_GAS;
+
+use aurora_engine::parameters::{SubmitResult, ViewCallArgs};
+
+mod common;
+
+const FEE_RECIPIENT: [u8; 20] = [0x42u8; 20];
+
+/// Init code deploying a contract which returns `block.coinbase`:
+/// `COINBASE PUSH1 0 MSTORE PUSH1 32 PUSH1 0 RETURN`.
+const COINBASE_CONTRACT: &str = "6009600c60003960096000f34160005260206000f3";
+
+#[test]
+fn test_coinbase_returns_fee_recipient() {
+    let (master_account, _contract_account) =
+        common::init_with(|args| args.fee_recipient = FEE_RECIPIENT);
+    let result: SubmitResult = master_account
+        .call(
+            accounts(0).to_string(),
+            "deploy_code",
+            &hex::decode(COINBASE_CONTRACT).unwrap(),
+            DEFAULT_GAS,
+            0,
+        )
+        .unwrap_borsh();
+    let mut address = [0u8; 20];
+    address.copy_from_slice(&result.result);
+
+    let output = master_account
+        .view(
+            accounts(0).to_string(),
+            "view",
+            &ViewCallArgs {
+                sender: [0u8; 20],
+                address,
+                amount: [0u8; 32],
+                input: Vec::new(),
+            }
+            .try_to_vec()
+            .unwrap(),
+        )
+        .unwrap();
+    assert_eq!(&output[12..], &FEE_RECIPIENT);
+}
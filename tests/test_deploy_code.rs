@@ -0,0 +1,11 @@
+use near_sdk::test_utils::accounts;
+use near_sdk_sim::DEFAULT_GAS;
+
+mod common;
+
+#[test]
+fn test_deploy_empty_code() {
+    let (master_account, _contract_account) = common::init();
+    let result = master_account.call(accounts(0).to_string(), "deploy_code", &[], DEFAULT_GAS, 0);
+    assert!(format!("{:?}", result.status()).contains("ERR_EMPTY_DEPLOY_CODE"));
+}
@@ -0,0 +1,68 @@
+use near_sdk::borsh::BorshSerialize;
+use near_sdk::test_utils::accounts;
+use near_sdk_sim::{UserAccount, DEFAULT_GAS};
+
+use aurora_engine::parameters::{SubmitResult, ViewCallArgs};
+use aurora_engine::types::near_account_to_evm_address;
+
+mod common;
+
+/// Init code deploying a contract which returns the EXTCODEHASH of the
+/// address passed as its calldata:
+/// `PUSH1 0 CALLDATALOAD EXTCODEHASH PUSH1 0 MSTORE PUSH1 32 PUSH1 0 RETURN`.
+const EXTCODEHASH_CONTRACT: &str = "600c600c600039600c6000f36000353f60005260206000f3";
+
+/// Keccak-256 hash of the empty code.
+const EMPTY_CODE_HASH: &str = "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470";
+
+fn deploy_extcodehash_contract(master_account: &UserAccount) -> [u8; 20] {
+    let result: SubmitResult = master_account
+        .call(
+            accounts(0).to_string(),
+            "deploy_code",
+            &hex::decode(EXTCODEHASH_CONTRACT).unwrap(),
+            DEFAULT_GAS,
+            0,
+        )
+        .unwrap_borsh();
+    let mut address = [0u8; 20];
+    address.copy_from_slice(&result.result);
+    address
+}
+
+fn extcodehash(master_account: &UserAccount, contract: [u8; 20], target: &[u8]) -> Vec<u8> {
+    let mut input = [0u8; 32];
+    input[12..].copy_from_slice(target);
+    master_account
+        .view(
+            accounts(0).to_string(),
+            "view",
+            &ViewCallArgs {
+                sender: [0u8; 20],
+                address: contract,
+                amount: [0u8; 32],
+                input: input.to_vec(),
+            }
+            .try_to_vec()
+            .unwrap(),
+        )
+        .unwrap()
+}
+
+#[test]
+fn test_extcodehash_of_absent_account() {
+    let (master_account, _contract_account) = common::init();
+    let contract = deploy_extcodehash_contract(&master_account);
+    let hash = extcodehash(&master_account, contract, &[0x42u8; 20]);
+    assert_eq!(hash, vec![0u8; 32]);
+}
+
+#[test]
+fn test_extcodehash_of_existing_account_without_code() {
+    let (master_account, _contract_account) = common::init();
+    let contract = deploy_extcodehash_contract(&master_account);
+    // Deploying bumped the deployer's nonce, so its address exists but has no code.
+    let deployer = near_account_to_evm_address(master_account.account_id.as_bytes());
+    let hash = extcodehash(&master_account, contract, deployer.as_bytes());
+    assert_eq!(hex::encode(hash), EMPTY_CODE_HASH);
+}
@@ -0,0 +1,80 @@
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::test_utils::accounts;
+use near_sdk_sim::{UserAccount, DEFAULT_GAS};
+
+use aurora_engine::parameters::{GetStorageSlotsArgs, SubmitResult};
+use aurora_engine::types::RawH256;
+
+mod common;
+
+/// Init code storing 42 in slot 1 and 7 in slot 3, and deploying no code:
+/// `PUSH1 42 PUSH1 1 SSTORE PUSH1 7 PUSH1 3 SSTORE STOP`.
+const STORING_CONTRACT: &str = "602a600155600760035500";
+
+fn deploy_storing_contract(master_account: &UserAccount) -> [u8; 20] {
+    let result: SubmitResult = master_account
+        .call(
+            accounts(0).to_string(),
+            "deploy_code",
+            &hex::decode(STORING_CONTRACT).unwrap(),
+            DEFAULT_GAS,
+            0,
+        )
+        .unwrap_borsh();
+    let mut address = [0u8; 20];
+    address.copy_from_slice(&result.result);
+    address
+}
+
+/// The storage key or value `n`.
+fn slot(n: u8) -> RawH256 {
+    let mut slot = [0u8; 32];
+    slot[31] = n;
+    slot
+}
+
+fn get_storage_slots_args(address: [u8; 20], keys: Vec<RawH256>) -> Vec<u8> {
+    GetStorageSlotsArgs { address, keys }.try_to_vec().unwrap()
+}
+
+#[test]
+fn test_get_storage_slots_in_order() {
+    let (master_account, _contract_account) = common::init();
+    let contract = deploy_storing_contract(&master_account);
+    let keys = vec![slot(3), slot(0), slot(1), slot(2), slot(3)];
+    let output = master_account
+        .view(
+            accounts(0).to_string(),
+            "get_storage_slots",
+            &get_storage_slots_args(contract, keys),
+        )
+        .unwrap();
+    let values = Vec::<RawH256>::try_from_slice(&output).unwrap();
+    // Absent slots read as zero, in the position of their key:
+    assert_eq!(values, vec![slot(7), slot(0), slot(42), slot(0), slot(7)]);
+}
+
+#[test]
+fn test_get_storage_slots_limit() {
+    let (master_account, _contract_account) = common::init();
+    let keys: Vec<RawH256> = (0..=255).map(slot).collect();
+    let output = master_account
+        .view(
+            accounts(0).to_string(),
+            "get_storage_slots",
+            &get_storage_slots_args([0u8; 20], keys.clone()),
+        )
+        .unwrap();
+    let values = Vec::<RawH256>::try_from_slice(&output).unwrap();
+    assert_eq!(values, vec![[0u8; 32]; 256]);
+
+    let keys = [keys, vec![slot(0)]].concat();
+    let result = master_account.call(
+        accounts(0).to_string(),
+        "get_storage_slots",
+        &get_storage_slots_args([0u8; 20], keys),
+        DEFAULT_GAS,
+        0,
+    );
+    assert!(format!("{:?}", result.status()).contains("ERR_TOO_MANY_STORAGE_SLOTS"));
+}
@@ -1,11 +1,14 @@
-use borsh::BorshSerialize;
+use borsh::{BorshDeserialize, BorshSerialize};
 
 use near_crypto::{InMemorySigner, KeyType, PublicKey, Signature, Signer};
 
-use aurora_engine::meta_parsing::{near_erc712_domain, parse_meta_call, prepare_meta_call_args};
-use aurora_engine::parameters::MetaCallArgs;
+use aurora_engine::meta_parsing::{
+    compute_struct_hash, near_erc712_domain, parse_meta_call, prepare_meta_call_args,
+    MAX_NESTING_DEPTH,
+};
+use aurora_engine::parameters::{MetaCallArgs, COMPACT_SIGNATURE_V};
 use aurora_engine::prelude::{Address, U256};
-use aurora_engine::types::{keccak, u256_to_arr, InternalMetaCallArgs};
+use aurora_engine::types::{keccak, u256_to_arr, ErrorKind, InternalMetaCallArgs};
 
 pub fn encode_meta_call_function_args(
     signer: &dyn Signer,
@@ -45,7 +48,7 @@ pub fn encode_meta_call_function_args(
             MetaCallArgs {
                 signature,
                 // Add 27 to align eth-sig-util signature format
-                v: 27,
+                v: 27 + array[64],
                 nonce: u256_to_arr(&nonce),
                 fee_amount: u256_to_arr(&fee_amount),
                 fee_address: fee_address.0,
@@ -117,3 +120,136 @@ fn test_meta_parsing() {
         .unwrap_or_else(|_| panic!("Fail meta_tx3"));
     assert_eq!(result.sender, signer_addr);
 }
+
+#[test]
+fn test_meta_parsing_rejects_altered_call() {
+    let chain_id = 1313161555;
+    let signer = InMemorySigner::from_seed("doesnt", KeyType::SECP256K1, "a");
+    let signer_addr = public_key_to_address(signer.public_key.clone());
+    let domain_separator = near_erc712_domain(U256::from(chain_id));
+
+    let meta_tx = encode_meta_call_function_args(
+        &signer,
+        chain_id,
+        U256::from(14),
+        U256::from(6),
+        Address::from_slice(&[0u8; 20]),
+        signer_addr.clone(),
+        U256::from(0),
+        "adopt(uint256 petId)",
+        // RLP encode of ["0x09"]
+        hex::decode("c109").unwrap(),
+    );
+    let alterations: [fn(&mut MetaCallArgs); 4] = [
+        // RLP encode of ["0x0a"]
+        |args| args.args = hex::decode("c10a").unwrap(),
+        |args| args.contract_address = [1u8; 20],
+        |args| args.value = u256_to_arr(&U256::from(1)),
+        |args| args.nonce = u256_to_arr(&U256::from(15)),
+    ];
+    for alter in alterations.iter() {
+        let mut args = MetaCallArgs::try_from_slice(&meta_tx).unwrap();
+        alter(&mut args);
+        // The signature no longer recovers to the signer, so it can't authorize the altered call.
+        if let Ok(result) = parse_meta_call(
+            &domain_separator,
+            "evm".as_bytes(),
+            args.try_to_vec().unwrap(),
+        ) {
+            assert_ne!(result.sender, signer_addr);
+        }
+    }
+
+    // Nor can it be replayed against another engine account.
+    if let Ok(result) = parse_meta_call(&domain_separator, "aurora".as_bytes(), meta_tx) {
+        assert_ne!(result.sender, signer_addr);
+    }
+}
+
+#[test]
+fn test_meta_parsing_compact_signature() {
+    let chain_id = 1313161555;
+    let signer = InMemorySigner::from_seed("doesnt", KeyType::SECP256K1, "a");
+    let signer_addr = public_key_to_address(signer.public_key.clone());
+    let domain_separator = near_erc712_domain(U256::from(chain_id));
+
+    // Find signatures of either y parity, as only an odd one changes `s` when compacted:
+    let mut parities = [false, false];
+    for nonce in 0..64 {
+        let meta_tx = encode_meta_call_function_args(
+            &signer,
+            chain_id,
+            U256::from(nonce),
+            U256::from(6),
+            Address::from_slice(&[0u8; 20]),
+            signer_addr.clone(),
+            U256::from(0),
+            "adopt(uint256 petId)",
+            // RLP encode of ["0x09"]
+            hex::decode("c109").unwrap(),
+        );
+        let mut args = MetaCallArgs::try_from_slice(&meta_tx).unwrap();
+        let y_parity = args.v - 27;
+        parities[y_parity as usize] = true;
+        args.signature[32] |= y_parity << 7;
+        args.v = COMPACT_SIGNATURE_V;
+        let compact = parse_meta_call(
+            &domain_separator,
+            "evm".as_bytes(),
+            args.try_to_vec().unwrap(),
+        )
+        .unwrap_or_else(|_| panic!("Fail compact meta_tx"));
+        let full = parse_meta_call(&domain_separator, "evm".as_bytes(), meta_tx.clone())
+            .unwrap_or_else(|_| panic!("Fail meta_tx"));
+        assert_eq!(compact.sender, signer_addr);
+        assert_eq!(full.sender, signer_addr);
+        // A `v` without 27 added is still a plain recovery id:
+        let mut args = MetaCallArgs::try_from_slice(&meta_tx).unwrap();
+        args.v = y_parity;
+        let plain = parse_meta_call(
+            &domain_separator,
+            "evm".as_bytes(),
+            args.try_to_vec().unwrap(),
+        )
+        .unwrap_or_else(|_| panic!("Fail plain meta_tx"));
+        assert_eq!(plain.sender, signer_addr);
+        if parities == [true, true] {
+            return;
+        }
+    }
+    panic!("No signatures of both y parities");
+}
+
+#[test]
+fn test_compute_struct_hash() {
+    // `Person` of the example in https://eips.ethereum.org/EIPS/eip-712,
+    // RLP encode of ["0x436f77", "0xcd2a3d9f938e13cd947ec05abc7fe734df8dd826"]
+    let values = hex::decode("d983436f7794cd2a3d9f938e13cd947ec05abc7fe734df8dd826").unwrap();
+    let hash = compute_struct_hash("Person(string name,address wallet)", &values).unwrap();
+    assert_eq!(
+        hex::encode(hash),
+        "fc71e5fa27ff56c350aa531bc129ebdf613b772b6604664f5d8dbe21b85eb0c8"
+    );
+
+    // Values must match the struct's fields: RLP encode of ["0x436f77"]
+    let values = hex::decode("c483436f77").unwrap();
+    assert!(compute_struct_hash("Person(string name,address wallet)", &values).is_err());
+}
+
+#[test]
+fn test_meta_parsing_too_nested() {
+    // RLP encode of `depth` nested lists: [[...[]...]]
+    let nested = |depth: usize| {
+        let mut rlp = vec![0xc0];
+        for _ in 1..depth {
+            rlp.insert(0, 0xc0 + rlp.len() as u8);
+        }
+        rlp
+    };
+    let type_def = "Node(Node child)Node(Node child)";
+    assert!(compute_struct_hash(type_def, &nested(MAX_NESTING_DEPTH + 1)).is_ok());
+    assert!(matches!(
+        compute_struct_hash(type_def, &nested(MAX_NESTING_DEPTH + 2)),
+        Err(ErrorKind::MetaTransactionTooNested)
+    ));
+}
@@ -0,0 +1,63 @@
+use near_sdk::test_utils::accounts;
+use near_sdk_sim::{ExecutionResult, UserAccount, DEFAULT_GAS};
+
+use aurora_engine::prelude::U256;
+use aurora_engine::types::u256_to_arr;
+
+mod common;
+
+const CHAIN_ID: u64 = 1313161556;
+
+fn raw_call(master_account: &UserAccount, signed_transaction: &[u8]) -> ExecutionResult {
+    master_account.call(
+        accounts(0).to_string(),
+        "raw_call",
+        signed_transaction,
+        DEFAULT_GAS,
+        0,
+    )
+}
+
+#[test]
+fn test_chain_id_optional() {
+    let (master_account, _contract_account) =
+        common::init_with(|args| args.chain_id = u256_to_arr(&U256::from(CHAIN_ID)));
+    let signer = common::eth_signer();
+    // Without EIP-155 replay protection:
+    let transaction = common::sign_transaction(&signer, common::transfer(0, 0), None);
+    raw_call(&master_account, &transaction).assert_success();
+    let transaction = common::sign_transaction(&signer, common::transfer(1, 0), Some(CHAIN_ID));
+    raw_call(&master_account, &transaction).assert_success();
+}
+
+#[test]
+fn test_chain_id_required() {
+    let (master_account, _contract_account) = common::init_with(|args| {
+        args.chain_id = u256_to_arr(&U256::from(CHAIN_ID));
+        args.require_chain_id = true;
+    });
+    let signer = common::eth_signer();
+    let transaction = common::sign_transaction(&signer, common::transfer(0, 0), None);
+    let result = raw_call(&master_account, &transaction);
+    assert!(format!("{:?}", result.status()).contains("ERR_CHAIN_ID_REQUIRED"));
+    let transaction = common::sign_transaction(&signer, common::transfer(0, 0), Some(CHAIN_ID));
+    raw_call(&master_account, &transaction).assert_success();
+}
+
+#[test]
+fn test_min_gas_price() {
+    const MIN_GAS_PRICE: u64 = 100;
+    let (master_account, _contract_account) =
+        common::init_with(|args| args.min_gas_price = u256_to_arr(&U256::from(MIN_GAS_PRICE)));
+    let signer = common::eth_signer();
+    let transaction = common::sign_transaction(&signer, common::transfer(0, 0), None);
+    let result = raw_call(&master_account, &transaction);
+    assert!(format!("{:?}", result.status()).contains("ERR_GAS_PRICE_TOO_LOW"));
+    let transaction =
+        common::sign_transaction(&signer, common::transfer(0, MIN_GAS_PRICE - 1), None);
+    let result = raw_call(&master_account, &transaction);
+    assert!(format!("{:?}", result.status()).contains("ERR_GAS_PRICE_TOO_LOW"));
+    // The minimum itself is accepted:
+    let transaction = common::sign_transaction(&signer, common::transfer(0, MIN_GAS_PRICE), None);
+    raw_call(&master_account, &transaction).assert_success();
+}
@@ -0,0 +1,43 @@
+use near_sdk::test_utils::accounts;
+use near_sdk_sim::DEFAULT_GAS;
+
+use aurora_engine::prelude::U256;
+use aurora_engine::transaction::{encode, EthSignedTransaction};
+use rlp::{Decodable, Rlp};
+
+mod common;
+
+/// A transfer signed for chain 1 by 0x2c7536e3605d9c16a7a3d7b1898e529396a65c23.
+const SIGNED_TRANSFER: &str = "f86a8086d55698372431831e848094f0109fc8df283027b6285cc889f5aa624eac1f55843b9aca008025a009ebb6ca057a0535d6186462bc0b465b561c94a295bdb0621fc19208ab149a9ca0440ffd775ce91a833ab410777204d5341a6f9fa91216a6f3ee2c051fea6a0428";
+
+#[test]
+fn test_recover_sender() {
+    let (master_account, _contract_account) = common::init();
+    let sender = master_account
+        .view(
+            accounts(0).to_string(),
+            "recover_sender",
+            &hex::decode(SIGNED_TRANSFER).unwrap(),
+        )
+        .unwrap();
+    assert_eq!(
+        hex::encode(sender),
+        "2c7536e3605d9c16a7a3d7b1898e529396a65c23"
+    );
+}
+
+#[test]
+fn test_recover_sender_invalid_signature() {
+    let (master_account, _contract_account) = common::init();
+    let encoded_tx = hex::decode(SIGNED_TRANSFER).unwrap();
+    let mut signed_transaction = EthSignedTransaction::decode(&Rlp::new(&encoded_tx)).unwrap();
+    signed_transaction.r = U256::zero();
+    let result = master_account.call(
+        accounts(0).to_string(),
+        "recover_sender",
+        &encode(&signed_transaction),
+        DEFAULT_GAS,
+        0,
+    );
+    assert!(format!("{:?}", result.status()).contains("ERR_INVALID_ECDSA_SIGNATURE"));
+}
@@ -0,0 +1,54 @@
+use near_sdk::borsh::BorshSerialize;
+use near_sdk::test_utils::accounts;
+use near_sdk_sim::{ExecutionResult, UserAccount, DEFAULT_GAS};
+
+use aurora_engine::parameters::{LogLevel, SetChainIdArgs};
+use aurora_engine::prelude::U256;
+use aurora_engine::types::u256_to_arr;
+
+mod common;
+
+const CHAIN_ID: u64 = 1313161556;
+
+fn set_chain_id(master_account: &UserAccount, chain_id: u64, confirm: bool) -> ExecutionResult {
+    let args = SetChainIdArgs {
+        chain_id: u256_to_arr(&U256::from(chain_id)),
+        confirm,
+    };
+    master_account.call(
+        accounts(0).to_string(),
+        "set_chain_id",
+        &args.try_to_vec().unwrap(),
+        DEFAULT_GAS,
+        0,
+    )
+}
+
+fn get_chain_id(master_account: &UserAccount) -> U256 {
+    let output = master_account
+        .view(accounts(0).to_string(), "get_chain_id", &[])
+        .unwrap();
+    U256::from_big_endian(&output)
+}
+
+#[test]
+fn test_set_chain_id_unconfirmed() {
+    let (master_account, _contract_account) = common::init();
+    let result = set_chain_id(&master_account, CHAIN_ID, false);
+    assert!(format!("{:?}", result.status()).contains("ERR_CHAIN_ID_CHANGE_UNCONFIRMED"));
+    assert_eq!(get_chain_id(&master_account), U256::zero());
+}
+
+#[test]
+fn test_set_chain_id_confirmed() {
+    // The warning is logged even with the engine's diagnostics turned off:
+    let (master_account, _contract_account) =
+        common::init_with(|args| args.log_level = LogLevel::Off);
+    let result = set_chain_id(&master_account, CHAIN_ID, true);
+    result.assert_success();
+    assert!(result
+        .logs()
+        .iter()
+        .any(|log| log.starts_with("WARNING: chain id changed from 0 to 1313161556")));
+    assert_eq!(get_chain_id(&master_account), U256::from(CHAIN_ID));
+}
@@ -1,55 +1,55 @@
-use near_sdk::borsh::BorshSerialize;
 use near_sdk::test_utils::accounts;
-use near_sdk_sim::{to_yocto, UserAccount, DEFAULT_GAS, STORAGE_AMOUNT};
+use near_sdk_sim::{ExecutionResult, UserAccount, DEFAULT_GAS};
 
-use aurora_engine::parameters::NewCallArgs;
+mod common;
 
-near_sdk_sim::lazy_static_include::lazy_static_include_bytes! {
-    EVM_WASM_BYTES => "release.wasm"
+fn call(account: &UserAccount, method_name: &str, args: &[u8]) -> ExecutionResult {
+    account.call(accounts(0).to_string(), method_name, args, DEFAULT_GAS, 0)
 }
 
-fn init() -> (UserAccount, UserAccount) {
-    let master_account = near_sdk_sim::init_simulator(None);
-    let contract_account =
-        master_account.deploy(*EVM_WASM_BYTES, accounts(0).to_string(), to_yocto("1000"));
-    contract_account
-        .call(
-            accounts(0).to_string(),
-            "new",
-            &NewCallArgs {
-                chain_id: [0u8; 32],
-                owner_id: master_account.account_id.clone(),
-                bridge_prover_id: accounts(0).to_string(),
-                upgrade_delay_blocks: 1,
-            }
-            .try_to_vec()
-            .unwrap(),
-            DEFAULT_GAS,
-            STORAGE_AMOUNT,
-        )
-        .assert_success();
-    (master_account, contract_account)
+fn status(result: ExecutionResult) -> String {
+    format!("{:?}", result.status())
+}
+
+fn stage_upgrade(master_account: &UserAccount) {
+    call(master_account, "stage_upgrade", common::evm_wasm_bytes()).assert_success();
 }
 
 #[test]
 fn test_contract_upgrade() {
-    let (master_account, _contract_account) = init();
-    master_account
-        .call(
-            accounts(0).to_string(),
-            "stage_upgrade",
-            &EVM_WASM_BYTES,
-            DEFAULT_GAS,
-            0,
-        )
-        .assert_success();
-    master_account
-        .call(
-            accounts(0).to_string(),
-            "deploy_upgrade",
-            &[],
-            DEFAULT_GAS,
-            0,
-        )
-        .assert_success();
+    let (master_account, _contract_account) = common::init();
+    stage_upgrade(&master_account);
+    call(&master_account, "get_upgrade_index", &[]).assert_success();
+    call(&master_account, "deploy_upgrade", &[]).assert_success();
+
+    // The successful deployment cleared the staged upgrade:
+    let result = call(&master_account, "get_upgrade_index", &[]);
+    assert!(status(result).contains("ERR_NO_UPGRADE"));
+    let result = call(&master_account, "deploy_upgrade", &[]);
+    assert!(status(result).contains("ERR_NO_UPGRADE"));
+}
+
+#[test]
+fn test_failed_upgrade_stays_staged() {
+    let (master_account, contract_account) =
+        common::init_with(|args| args.upgrade_delay_blocks = 1_000);
+    stage_upgrade(&master_account);
+    let result = call(&master_account, "deploy_upgrade", &[]);
+    assert!(status(result).contains("ERR_NOT_ALLOWED:TOO_EARLY"));
+    call(&master_account, "get_upgrade_index", &[]).assert_success();
+
+    // The deployment itself can't be made to fail in the simulator, so the
+    // callback is run without the successful result of one:
+    call(&contract_account, "finish_upgrade", &[]).assert_success();
+    call(&master_account, "get_upgrade_index", &[]).assert_success();
+    let result = call(&master_account, "finish_upgrade", &[]);
+    assert!(status(result).contains("ERR_NOT_ALLOWED"));
+    call(&master_account, "get_upgrade_index", &[]).assert_success();
+}
+
+#[test]
+fn test_deploy_upgrade_without_staged_code() {
+    let (master_account, _contract_account) = common::init();
+    let result = call(&master_account, "deploy_upgrade", &[]);
+    assert!(status(result).contains("ERR_NO_UPGRADE"));
 }